@@ -0,0 +1,422 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use fluvio_connector_common::tracing::{debug, warn};
+use http::{header::AUTHORIZATION, HeaderName, HeaderValue};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::{
+    config::{ApiKeyLocation, AuthConfig, OAuth2ClientAuth, OAuth2Config, OAuth2GrantType},
+    pagination::set_query_param,
+};
+
+/// Time limit of token requests when the connector has no `timeout`
+const DEFAULT_TOKEN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Applies the configured authentication to outgoing requests.
+///
+/// Cloned instances share the same token cache, so a token obtained by one source
+/// is reused after reconnects.
+#[derive(Clone)]
+pub(crate) enum Authenticator {
+    /// Credentials that do not change, resolved once at startup
+    Static(Credential),
+    OAuth2(Arc<OAuth2Client>),
+}
+
+/// Where credentials are placed in a request
+#[derive(Clone)]
+pub(crate) enum Credential {
+    Header(HeaderName, HeaderValue),
+    Query(String, String),
+}
+
+impl Authenticator {
+    /// `client` sends the OAuth2 token requests, with the `tls` and `proxy` settings of the connector,
+    /// each limited to `timeout` or `DEFAULT_TOKEN_TIMEOUT`
+    pub(crate) fn new(
+        config: &AuthConfig,
+        client: &Client,
+        timeout: Option<Duration>,
+    ) -> Result<Self> {
+        match config {
+            AuthConfig::Basic(config) => {
+                let username = config.username.resolve()?;
+                let password = config
+                    .password
+                    .as_ref()
+                    .map(|password| password.resolve())
+                    .transpose()?
+                    .unwrap_or_default();
+                let encoded = STANDARD.encode(format!("{username}:{password}"));
+
+                Ok(Self::Static(Credential::Header(
+                    AUTHORIZATION,
+                    sensitive_header_value(&format!("Basic {encoded}"))?,
+                )))
+            }
+            AuthConfig::Bearer(config) => Ok(Self::Static(Credential::Header(
+                AUTHORIZATION,
+                sensitive_header_value(&format!("Bearer {}", config.token.resolve()?))?,
+            ))),
+            AuthConfig::ApiKey(config) => {
+                let value = config.value.resolve()?;
+                let credential = match config.location {
+                    ApiKeyLocation::Header => Credential::Header(
+                        HeaderName::from_bytes(config.name.as_bytes())
+                            .context("auth.name is not a valid header name")?,
+                        sensitive_header_value(&value)?,
+                    ),
+                    ApiKeyLocation::Query => Credential::Query(config.name.clone(), value),
+                };
+
+                Ok(Self::Static(credential))
+            }
+            AuthConfig::OAuth2(config) => Ok(Self::OAuth2(Arc::new(OAuth2Client::new(
+                config,
+                client.clone(),
+                timeout.unwrap_or(DEFAULT_TOKEN_TIMEOUT),
+            )?))),
+        }
+    }
+
+    /// Adds credentials to a request sent with reqwest
+    pub(crate) async fn authorize(&self, request: &mut reqwest::Request) -> Result<()> {
+        match self.credential().await? {
+            Credential::Header(name, value) => {
+                request.headers_mut().insert(name, value);
+            }
+            Credential::Query(name, value) => set_query_param(request.url_mut(), &name, &value),
+        }
+
+        Ok(())
+    }
+
+    /// Adds credentials to a WebSocket handshake request
+    pub(crate) async fn authorize_http<T>(&self, request: &mut http::Request<T>) -> Result<()> {
+        match self.credential().await? {
+            Credential::Header(name, value) => {
+                request.headers_mut().insert(name, value);
+            }
+            Credential::Query(name, value) => {
+                let mut url = Url::parse(&request.uri().to_string())?;
+                set_query_param(&mut url, &name, &value);
+                *request.uri_mut() = url.as_str().parse()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Discards cached credentials after the server rejected them
+    pub(crate) async fn invalidate(&self) {
+        match self {
+            Self::Static(_) => {}
+            Self::OAuth2(client) => client.invalidate().await,
+        }
+    }
+
+    async fn credential(&self) -> Result<Credential> {
+        match self {
+            Self::Static(credential) => Ok(credential.clone()),
+            Self::OAuth2(client) => {
+                let token = client.access_token().await?;
+
+                Ok(Credential::Header(
+                    AUTHORIZATION,
+                    sensitive_header_value(&format!("Bearer {token}"))?,
+                ))
+            }
+        }
+    }
+}
+
+fn sensitive_header_value(value: &str) -> Result<HeaderValue> {
+    let mut value =
+        HeaderValue::from_str(value).context("credential is not a valid header value")?;
+    value.set_sensitive(true);
+
+    Ok(value)
+}
+
+/// Obtains access tokens from an OAuth2 token endpoint and caches them until they expire.
+pub(crate) struct OAuth2Client {
+    client: Client,
+    timeout: Duration,
+    token_url: Url,
+    client_id: String,
+    client_secret: Option<String>,
+    grant_type: OAuth2GrantType,
+    refresh_token: Option<String>,
+    scopes: Vec<String>,
+    audience: Option<String>,
+    client_auth: OAuth2ClientAuth,
+    refresh_margin: Duration,
+    token: Mutex<Option<Token>>,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    access_token: String,
+    expires_at: Option<Instant>,
+    refresh_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+impl Token {
+    fn from_response(response: TokenResponse, now: Instant) -> Self {
+        Self {
+            access_token: response.access_token,
+            expires_at: response
+                .expires_in
+                .map(|expires_in| now + Duration::from_secs(expires_in)),
+            refresh_token: response.refresh_token,
+        }
+    }
+
+    fn is_valid(&self, now: Instant, margin: Duration) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| now + margin < expires_at)
+    }
+}
+
+impl OAuth2Client {
+    fn new(config: &OAuth2Config, client: Client, timeout: Duration) -> Result<Self> {
+        let refresh_token = config
+            .refresh_token
+            .as_ref()
+            .map(|token| token.resolve())
+            .transpose()?;
+
+        if config.grant_type == OAuth2GrantType::RefreshToken && refresh_token.is_none() {
+            return Err(anyhow!(
+                "auth.refresh_token is required for the refresh_token grant type"
+            ));
+        }
+
+        Ok(Self {
+            client,
+            timeout,
+            token_url: Url::parse(&config.token_url.resolve()?)
+                .context("unable to parse auth.token_url")?,
+            client_id: config.client_id.resolve()?,
+            client_secret: config
+                .client_secret
+                .as_ref()
+                .map(|secret| secret.resolve())
+                .transpose()?,
+            grant_type: config.grant_type,
+            refresh_token,
+            scopes: config.scopes.clone(),
+            audience: config.audience.clone(),
+            client_auth: config.client_auth,
+            refresh_margin: config.refresh_margin,
+            token: Mutex::new(None),
+        })
+    }
+
+    async fn access_token(&self) -> Result<String> {
+        let mut token = self.token.lock().await;
+
+        if let Some(ref current) = *token {
+            if current.is_valid(Instant::now(), self.refresh_margin) {
+                return Ok(current.access_token.clone());
+            }
+        }
+
+        let refresh_token = token
+            .as_ref()
+            .and_then(|current| current.refresh_token.clone())
+            .or_else(|| self.refresh_token.clone());
+
+        let new_token = match refresh_token {
+            Some(refresh_token) => match self.refresh(&refresh_token).await {
+                Ok(new_token) => new_token,
+                Err(err) if self.grant_type == OAuth2GrantType::ClientCredentials => {
+                    warn!("Token refresh failed, requesting a new token: {err:?}");
+                    self.client_credentials().await?
+                }
+                Err(err) => return Err(err),
+            },
+            None => self.client_credentials().await?,
+        };
+
+        let access_token = new_token.access_token.clone();
+        *token = Some(new_token);
+
+        Ok(access_token)
+    }
+
+    async fn invalidate(&self) {
+        if let Some(ref mut current) = *self.token.lock().await {
+            current.expires_at = Some(Instant::now());
+        }
+    }
+
+    async fn client_credentials(&self) -> Result<Token> {
+        let mut params = vec![("grant_type", "client_credentials".to_string())];
+        if !self.scopes.is_empty() {
+            params.push(("scope", self.scopes.join(" ")));
+        }
+        if let Some(ref audience) = self.audience {
+            params.push(("audience", audience.clone()));
+        }
+
+        self.request_token(params).await
+    }
+
+    async fn refresh(&self, refresh_token: &str) -> Result<Token> {
+        let mut params = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token.to_string()),
+        ];
+        if !self.scopes.is_empty() {
+            params.push(("scope", self.scopes.join(" ")));
+        }
+
+        let mut token = self.request_token(params).await?;
+        // servers that do not rotate refresh tokens omit them from the response
+        token
+            .refresh_token
+            .get_or_insert_with(|| refresh_token.to_string());
+
+        Ok(token)
+    }
+
+    async fn request_token(&self, mut params: Vec<(&'static str, String)>) -> Result<Token> {
+        let mut request = self
+            .client
+            .post(self.token_url.clone())
+            .timeout(self.timeout);
+        match self.client_auth {
+            OAuth2ClientAuth::Basic => {
+                request = request.basic_auth(&self.client_id, self.client_secret.as_ref());
+            }
+            OAuth2ClientAuth::Body => {
+                params.push(("client_id", self.client_id.clone()));
+                if let Some(ref client_secret) = self.client_secret {
+                    params.push(("client_secret", client_secret.clone()));
+                }
+            }
+        }
+
+        let now = Instant::now();
+        let response = request
+            .form(&params)
+            .send()
+            .await
+            .context("send token request")?;
+        let status = response.status();
+        let body = response.text().await.context("read token response")?;
+
+        if !status.is_success() {
+            return Err(anyhow!("token endpoint responded with {status}: {body}"));
+        }
+
+        let response: TokenResponse =
+            serde_json::from_str(&body).context("unable to parse token response")?;
+        debug!(expires_in = ?response.expires_in, "Obtained OAuth2 access token");
+
+        Ok(Token::from_response(response, now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_expiry_respects_margin() {
+        let now = Instant::now();
+        let token = Token::from_response(
+            serde_json::from_str(r#"{"access_token":"abc","token_type":"Bearer","expires_in":60}"#)
+                .unwrap(),
+            now,
+        );
+
+        assert_eq!(token.access_token, "abc");
+        assert!(token.is_valid(now, Duration::from_secs(30)));
+        assert!(!token.is_valid(now + Duration::from_secs(31), Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_token_without_expiry_stays_valid() {
+        let now = Instant::now();
+        let token = Token::from_response(
+            serde_json::from_str(r#"{"access_token":"abc","refresh_token":"r1"}"#).unwrap(),
+            now,
+        );
+
+        assert_eq!(token.refresh_token.as_deref(), Some("r1"));
+        assert!(token.is_valid(now + Duration::from_secs(3600), Duration::from_secs(30)));
+    }
+
+    #[async_std::test]
+    async fn test_static_credentials() {
+        let mut request = reqwest::Request::new(
+            reqwest::Method::GET,
+            Url::parse("http://localhost/items?key=old").unwrap(),
+        );
+
+        let basic: AuthConfig =
+            serde_json::from_str(r#"{"type":"basic","username":"user","password":"pass"}"#)
+                .unwrap();
+        Authenticator::new(&basic, &Client::new(), None)
+            .unwrap()
+            .authorize(&mut request)
+            .await
+            .unwrap();
+        assert_eq!(request.headers()[AUTHORIZATION], "Basic dXNlcjpwYXNz");
+
+        let api_key: AuthConfig = serde_json::from_str(
+            r#"{"type":"api_key","name":"key","value":"s3cret","in":"query"}"#,
+        )
+        .unwrap();
+        Authenticator::new(&api_key, &Client::new(), None)
+            .unwrap()
+            .authorize(&mut request)
+            .await
+            .unwrap();
+        assert_eq!(request.url().as_str(), "http://localhost/items?key=s3cret");
+    }
+
+    #[async_std::test]
+    async fn test_token_request_times_out() {
+        // accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        async_std::task::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let oauth2: AuthConfig = serde_json::from_str(&format!(
+            r#"{{"type":"oauth2","token_url":"http://{address}/token","client_id":"id","client_secret":"secret"}}"#
+        ))
+        .unwrap();
+        let auth =
+            Authenticator::new(&oauth2, &Client::new(), Some(Duration::from_millis(200))).unwrap();
+        let mut request = reqwest::Request::new(
+            reqwest::Method::GET,
+            Url::parse("http://localhost/").unwrap(),
+        );
+
+        let started = Instant::now();
+        assert!(auth.authorize(&mut request).await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,22 +1,297 @@
-use std::time::Duration;
+use std::{fmt, time::Duration};
 
-pub(crate) enum Backoff {
-    Fib(u64, u64),
+use serde::Deserialize;
+
+use crate::status_policy::RejectedStatus;
+
+const DEFAULT_INITIAL_DELAY: Duration = Duration::from_millis(10);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(300);
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_REQUEST_ATTEMPTS: u32 = 5;
+
+/// Delays between attempts to connect or to send a request.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct RetryPolicy {
+    /// How the delay grows between attempts: exponential | fibonacci | constant
+    #[serde(default = "Default::default")]
+    pub strategy: BackoffStrategy,
+
+    /// Delay after the first failed attempt
+    #[serde(with = "humantime_serde", default = "default_initial_delay")]
+    pub initial_delay: Duration,
+
+    /// Upper bound of the delay
+    #[serde(with = "humantime_serde", default = "default_max_delay")]
+    pub max_delay: Duration,
+
+    /// Growth factor of the exponential strategy
+    #[serde(default = "default_multiplier")]
+    pub multiplier: f64,
+
+    /// Fraction of the delay randomly taken off, from 0.0 to 1.0
+    #[serde(default = "Default::default")]
+    pub jitter: f64,
+
+    /// Attempts before giving up; when unset, connections are retried indefinitely and polling
+    /// requests are attempted `DEFAULT_REQUEST_ATTEMPTS` times
+    #[serde(default = "Default::default")]
+    pub max_attempts: Option<u32>,
+
+    /// What happens after max_attempts: exit | keep_trying
+    #[serde(default = "Default::default")]
+    pub on_give_up: GiveUpAction,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BackoffStrategy {
+    /// `initial_delay` multiplied by `multiplier` after every attempt
+    Exponential,
+    /// `initial_delay` multiplied by the Fibonacci sequence
+    #[default]
+    Fibonacci,
+    /// `initial_delay` after every attempt
+    Constant,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum GiveUpAction {
+    /// Stop the connector with an error
+    #[default]
+    Exit,
+    /// Log an error and continue, reconnecting from `initial_delay` or moving on to the next tick
+    KeepTrying,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            strategy: Default::default(),
+            initial_delay: DEFAULT_INITIAL_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            multiplier: DEFAULT_MULTIPLIER,
+            jitter: 0.0,
+            max_attempts: None,
+            on_give_up: Default::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy of a polling request, which is attempted at most `DEFAULT_REQUEST_ATTEMPTS` times
+    /// per tick when `max_attempts` is unset so a failing endpoint does not stall the tick
+    pub fn per_request(&self) -> Self {
+        Self {
+            max_attempts: self.max_attempts.or(Some(DEFAULT_REQUEST_ATTEMPTS)),
+            ..self.clone()
+        }
+    }
+}
+
+/// Attempts and delays of a single operation following a [`RetryPolicy`].
+pub(crate) struct Backoff {
+    policy: RetryPolicy,
+    failures: u32,
+    /// Fibonacci factors of the current and next delay
+    fib: (u32, u32),
 }
 
 impl Backoff {
-    pub fn new() -> Self {
-        Backoff::Fib(1, 1)
+    pub fn new(policy: &RetryPolicy) -> Self {
+        Backoff {
+            policy: policy.clone(),
+            failures: 0,
+            fib: (1, 1),
+        }
     }
 
-    pub fn next(&mut self) -> Duration {
-        match self {
-            Backoff::Fib(a, b) => {
-                let next = *a + *b;
-                *self = Backoff::Fib(*b, next);
+    /// Records a failed attempt, returning the delay before the next one
+    /// or `None` once `max_attempts` attempts failed
+    pub fn next(&mut self) -> Option<Duration> {
+        self.failures += 1;
+        if self
+            .policy
+            .max_attempts
+            .is_some_and(|max_attempts| self.failures >= max_attempts)
+        {
+            return None;
+        }
+
+        Some(self.delay())
+    }
 
-                std::time::Duration::from_millis(10 * next)
+    /// Failed attempts so far
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Starts over from `initial_delay` after a success
+    pub fn reset(&mut self) {
+        self.failures = 0;
+        self.fib = (1, 1);
+    }
+
+    fn delay(&mut self) -> Duration {
+        let policy = &self.policy;
+        let delay = match policy.strategy {
+            BackoffStrategy::Constant => policy.initial_delay,
+            BackoffStrategy::Exponential => {
+                let exponent = self.failures.saturating_sub(1).min(i32::MAX as u32) as i32;
+                policy.initial_delay.mul_f64(
+                    policy
+                        .multiplier
+                        .max(1.0)
+                        .powi(exponent)
+                        .min(u32::MAX as f64),
+                )
             }
+            BackoffStrategy::Fibonacci => {
+                let (current, next) = self.fib;
+                self.fib = (next, current.saturating_add(next));
+                policy.initial_delay.saturating_mul(current)
+            }
+        };
+        let delay = delay.min(policy.max_delay);
+
+        match policy.jitter.clamp(0.0, 1.0) {
+            jitter if jitter > 0.0 => delay.mul_f64(1.0 - jitter * fastrand::f64()),
+            _ => delay,
         }
     }
 }
+
+/// Context of an error returned after `max_attempts` failed attempts with `on_give_up: exit`
+#[derive(Debug)]
+pub(crate) struct RetriesExhausted {
+    pub attempts: u32,
+}
+
+impl fmt::Display for RetriesExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "giving up after {} attempts", self.attempts)
+    }
+}
+
+/// Context of an error returned when a source cannot be created, e.g. because of an invalid `tls` file
+#[derive(Debug)]
+pub(crate) struct SetupFailed;
+
+impl fmt::Display for SetupFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unable to create source")
+    }
+}
+
+/// Errors that stop the connector instead of being retried
+pub(crate) fn is_fatal(err: &anyhow::Error) -> bool {
+    err.is::<RejectedStatus>() || err.is::<RetriesExhausted>() || err.is::<SetupFailed>()
+}
+
+fn default_initial_delay() -> Duration {
+    DEFAULT_INITIAL_DELAY
+}
+
+fn default_max_delay() -> Duration {
+    DEFAULT_MAX_DELAY
+}
+
+fn default_multiplier() -> f64 {
+    DEFAULT_MULTIPLIER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delays(policy: RetryPolicy, count: usize) -> Vec<Option<Duration>> {
+        let mut backoff = Backoff::new(&policy);
+        (0..count).map(|_| backoff.next()).collect()
+    }
+
+    fn millis(delays: &[u64]) -> Vec<Option<Duration>> {
+        delays
+            .iter()
+            .map(|&delay| Some(Duration::from_millis(delay)))
+            .collect()
+    }
+
+    #[test]
+    fn test_strategies() {
+        let policy = RetryPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            delays(policy.clone(), 7),
+            millis(&[100, 100, 200, 300, 500, 800, 1000])
+        );
+        assert_eq!(
+            delays(
+                RetryPolicy {
+                    strategy: BackoffStrategy::Exponential,
+                    ..policy.clone()
+                },
+                5
+            ),
+            millis(&[100, 200, 400, 800, 1000])
+        );
+        assert_eq!(
+            delays(
+                RetryPolicy {
+                    strategy: BackoffStrategy::Constant,
+                    ..policy
+                },
+                3
+            ),
+            millis(&[100, 100, 100])
+        );
+    }
+
+    #[test]
+    fn test_max_attempts_and_jitter() {
+        let policy: RetryPolicy = serde_json::from_str(
+            r#"{"strategy":"constant","initial_delay":"1s","max_attempts":3,"jitter":0.5}"#,
+        )
+        .unwrap();
+        assert_eq!(policy.on_give_up, GiveUpAction::Exit);
+
+        let delays = delays(policy, 3);
+        for delay in delays[..2].iter() {
+            let delay = delay.unwrap();
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1));
+        }
+        assert_eq!(delays[2], None);
+    }
+
+    #[test]
+    fn test_requests_are_attempted_a_bounded_number_of_times() {
+        let policy = RetryPolicy::default().per_request();
+        let delays = delays(policy, DEFAULT_REQUEST_ATTEMPTS as usize);
+        assert!(delays[..DEFAULT_REQUEST_ATTEMPTS as usize - 1]
+            .iter()
+            .all(Option::is_some));
+        assert_eq!(delays.last(), Some(&None));
+
+        let policy = RetryPolicy {
+            max_attempts: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(policy.per_request().max_attempts, Some(10));
+    }
+
+    #[test]
+    fn test_fatal_errors() {
+        let err = anyhow::anyhow!("connection refused").context(RetriesExhausted { attempts: 3 });
+        assert!(is_fatal(&err));
+        let err = anyhow::anyhow!("invalid tls.ca_cert").context(SetupFailed);
+        assert!(is_fatal(&err));
+        assert!(!is_fatal(&anyhow::anyhow!("connection refused")));
+    }
+}
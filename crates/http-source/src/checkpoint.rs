@@ -0,0 +1,216 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Context, Result};
+use fluvio::{
+    consumer::ConsumerConfigExtBuilder, metadata::topic::TopicSpec, Fluvio, Offset, RecordKey,
+    TopicProducerPool,
+};
+use fluvio_connector_common::tracing::{debug, info};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::CheckpointConfig, poll_state::PollState};
+
+/// Source state saved after records are produced and restored at startup.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Checkpoint {
+    #[serde(default)]
+    pub poll: PollState,
+    /// `now` of the last tick whose requests all succeeded
+    #[serde(default, with = "humantime_serde")]
+    pub last_success_time: Option<SystemTime>,
+}
+
+/// Backend where checkpoints are kept
+pub(crate) enum CheckpointStore {
+    File(PathBuf),
+    Topic {
+        topic: String,
+        /// Endpoint name, records of other endpoints sharing the topic are skipped
+        key: Option<String>,
+        producer: TopicProducerPool,
+    },
+}
+
+impl CheckpointStore {
+    /// Creates the store of a single endpoint, `name` is set when `endpoints` is used
+    pub(crate) async fn new(config: &CheckpointConfig, name: Option<&str>) -> Result<Self> {
+        match config {
+            CheckpointConfig::File { path } => Ok(Self::File(match name {
+                Some(name) => endpoint_path(path, name),
+                None => path.clone(),
+            })),
+            CheckpointConfig::Topic { topic } => {
+                check_single_partition(topic).await?;
+                let producer = fluvio::producer(topic.as_str()).await.with_context(|| {
+                    format!("unable to create producer for checkpoint topic {topic}")
+                })?;
+
+                Ok(Self::Topic {
+                    topic: topic.clone(),
+                    key: name.map(String::from),
+                    producer,
+                })
+            }
+        }
+    }
+
+    /// Reads the latest checkpoint, `None` when nothing was saved yet
+    pub(crate) async fn load(&self) -> Result<Option<Checkpoint>> {
+        let data = match self {
+            Self::File(path) => match async_std::fs::read(path).await {
+                Ok(data) => Some(data),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("unable to read checkpoint file {}", path.display())
+                    })
+                }
+            },
+            Self::Topic { topic, key, .. } => last_record(topic, key.as_deref()).await?,
+        };
+
+        let checkpoint = data
+            .map(|data| serde_json::from_slice(&data))
+            .transpose()
+            .context("unable to parse checkpoint")?;
+        if checkpoint.is_some() {
+            info!("Resuming from saved checkpoint");
+        }
+
+        Ok(checkpoint)
+    }
+
+    pub(crate) async fn save(&self, checkpoint: &Checkpoint) -> Result<()> {
+        let data = serde_json::to_vec(checkpoint)?;
+
+        match self {
+            Self::File(path) => {
+                // write to a temporary file first so a crash never leaves a partial checkpoint
+                let tmp = path.with_extension("tmp");
+                async_std::fs::write(&tmp, data)
+                    .await
+                    .with_context(|| format!("unable to write {}", tmp.display()))?;
+                async_std::fs::rename(&tmp, path)
+                    .await
+                    .with_context(|| format!("unable to replace {}", path.display()))?;
+            }
+            Self::Topic { producer, key, .. } => {
+                let key = match key {
+                    Some(key) => RecordKey::from(key.clone()),
+                    None => RecordKey::NULL,
+                };
+                producer.send(key, data).await?;
+                producer.flush().await?;
+            }
+        }
+        debug!("Checkpoint saved");
+
+        Ok(())
+    }
+}
+
+/// Inserts the endpoint name before the extension, e.g. `state.json` -> `state-orders.json`
+fn endpoint_path(path: &Path, name: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{stem}-{name}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{name}"),
+    };
+
+    path.with_file_name(file_name)
+}
+
+/// Checkpoints are only read from partition 0, while keyed records would be spread over
+/// every partition of the topic
+async fn check_single_partition(topic: &str) -> Result<()> {
+    let admin = Fluvio::connect().await?.admin().await;
+    let topics = admin
+        .list::<TopicSpec, _>(vec![topic.to_string()])
+        .await
+        .with_context(|| format!("unable to look up checkpoint topic {topic}"))?;
+    let partitions = topics
+        .first()
+        .ok_or_else(|| anyhow!("checkpoint topic {topic} not found"))?
+        .spec
+        .partitions();
+
+    match partitions {
+        1 => Ok(()),
+        partitions => Err(anyhow!(
+            "checkpoint topic {topic} has {partitions} partitions, it must have a single partition"
+        )),
+    }
+}
+
+/// Value of the last record of the topic, or of the last record with `key` when set
+async fn last_record(topic: &str, key: Option<&str>) -> Result<Option<Vec<u8>>> {
+    let fluvio = Fluvio::connect().await?;
+    // records of all endpoints share the topic, so a keyed lookup reads it from the beginning
+    let offset = match key {
+        Some(_) => Offset::beginning(),
+        None => Offset::from_end(1),
+    };
+    let config = ConsumerConfigExtBuilder::default()
+        .topic(topic)
+        .partition(0)
+        .offset_start(offset)
+        .disable_continuous(true)
+        .build()?;
+    let mut stream = Box::pin(
+        fluvio
+            .consumer_with_config(config)
+            .await
+            .with_context(|| format!("unable to consume checkpoint topic {topic}"))?,
+    );
+
+    let mut last = None;
+    while let Some(record) = stream.next().await {
+        let record = record?;
+        if key.is_none() || record.key() == key.map(str::as_bytes) {
+            last = Some(record.value().to_vec());
+        }
+    }
+
+    Ok(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn test_file_store_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "http-source-checkpoint-{}.json",
+            std::process::id()
+        ));
+        let store = CheckpointStore::File(path.clone());
+
+        assert_eq!(store.load().await.unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            last_success_time: Some(SystemTime::UNIX_EPOCH),
+            ..Default::default()
+        };
+        store.save(&checkpoint).await.unwrap();
+        assert_eq!(store.load().await.unwrap(), Some(checkpoint));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_endpoint_path() {
+        assert_eq!(
+            endpoint_path(Path::new("/var/lib/http/state.json"), "orders"),
+            PathBuf::from("/var/lib/http/state-orders.json")
+        );
+        assert_eq!(
+            endpoint_path(Path::new("state"), "orders"),
+            PathBuf::from("state-orders")
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use reqwest::{Client, ClientBuilder};
+
+use crate::{
+    config::{Compression, HttpConfig},
+    proxy::ProxySettings,
+    tls,
+};
+
+/// Builder of the HTTP clients of the polling and streaming sources
+pub(crate) fn builder(config: &HttpConfig) -> Result<ClientBuilder> {
+    // reqwest negotiates and decodes every encoding enabled at build time
+    let mut builder = Client::builder()
+        .gzip(false)
+        .deflate(false)
+        .brotli(false)
+        .zstd(false);
+
+    for compression in config.compression.iter() {
+        builder = match compression {
+            Compression::Gzip => builder.gzip(true),
+            Compression::Deflate => builder.deflate(true),
+            Compression::Brotli => builder.brotli(true),
+            Compression::Zstd => builder.zstd(true),
+        };
+    }
+
+    if let Some(proxy) = &config.proxy {
+        builder = builder.proxy(ProxySettings::new(proxy)?.reqwest_proxy()?);
+    }
+
+    if let Some(tls_config) = &config.tls {
+        builder = builder.use_preconfigured_tls((*tls::client_config(tls_config)?).clone());
+    }
+
+    Ok(builder)
+}
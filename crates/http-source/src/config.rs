@@ -1,18 +1,37 @@
-use std::time::Duration;
+use std::{collections::HashSet, path::PathBuf, time::Duration};
 
+use anyhow::{anyhow, Result};
 use fluvio_connector_common::{connector, secret::SecretString};
 use serde::Deserialize;
 
+use crate::{
+    backoff::RetryPolicy, proxy::ProxyConfig, record_selector::RecordSelector, schedule::Schedule,
+    status_policy::StatusPolicy, template::Template, tls::TlsConfig,
+};
+
 const DEFAULT_USER_AGENT: &str = "fluvio/http-source 0.5.0";
 const DEFAULT_HTTP_METHOD: &str = "GET";
 const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
 const DEFAULT_DELIMITER: &str = "\n";
+const DEFAULT_PAGE_PARAM: &str = "page";
+const DEFAULT_START_PAGE: u64 = 1;
+const DEFAULT_OFFSET_PARAM: &str = "offset";
+const DEFAULT_CURSOR_PARAM: &str = "cursor";
+const DEFAULT_TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(30);
+const DEFAULT_FAN_OUT_CONCURRENCY: usize = 4;
+const DEFAULT_MAX_IN_FLIGHT: usize = 1;
+const DEFAULT_ACK_TIMEOUT_MS: u64 = 10_000;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[connector(config, name = "http")]
 pub(crate) struct HttpConfig {
     /// Endpoint for the http connector
-    pub endpoint: SecretString,
+    #[serde(default = "Default::default")]
+    pub endpoint: Option<SecretString>,
+
+    /// Named endpoints polled concurrently, each overriding settings of this config
+    #[serde(default = "Vec::new")]
+    pub endpoints: Vec<EndpointConfig>,
 
     /// HTTP body for the request
     pub body: Option<String>,
@@ -30,6 +49,23 @@ pub(crate) struct HttpConfig {
     #[serde(with = "humantime_serde", default = "default_interval")]
     pub interval: Duration,
 
+    /// Cron expression and timezone of the polling ticks, replaces `interval`
+    #[serde(default = "Default::default")]
+    pub schedule: Option<Schedule>,
+
+    /// Ticks polled at the same time, a slow tick delays the next one when reached
+    #[serde(default = "default_max_in_flight")]
+    pub max_in_flight: usize,
+
+    /// What happens to ticks missed while polling is delayed: burst | delay | skip
+    #[serde(default = "Default::default")]
+    pub missed_tick: MissedTick,
+
+    /// Time limit of each request in polling mode, including reading the body
+    /// Ex: '5s', '500ms'
+    #[serde(default = "Default::default", with = "humantime_serde")]
+    pub timeout: Option<Duration>,
+
     /// Indicate streaming mode, defaults to false
     #[serde(default = "Default::default")]
     pub stream: bool,
@@ -38,10 +74,34 @@ pub(crate) struct HttpConfig {
     #[serde(default = "default_delimiter")]
     pub delimiter: String,
 
+    /// Format of the response stream in streaming mode: delimited | sse
+    #[serde(default = "Default::default")]
+    pub stream_format: StreamFormat,
+
+    /// Include the name and id of Server-Sent Events in JSON output
+    #[serde(default = "Default::default")]
+    pub sse_metadata: bool,
+
+    /// Response encodings negotiated with `Accept-Encoding` and decoded: gzip | deflate | brotli | zstd
+    #[serde(default = "Vec::new")]
+    pub compression: Vec<Compression>,
+
     /// Headers to include in the HTTP request, in "Key=Value" format
     #[serde(default = "Vec::new")]
     pub headers: Vec<SecretString>,
 
+    /// Custom CA certificates, client certificate, protocol version and public key pinning
+    #[serde(default = "Default::default")]
+    pub tls: Option<TlsConfig>,
+
+    /// HTTP or SOCKS proxy of the requests and WebSocket connections
+    #[serde(default = "Default::default")]
+    pub proxy: Option<ProxyConfig>,
+
+    /// Authentication applied to every request
+    #[serde(default = "Default::default")]
+    pub auth: Option<AuthConfig>,
+
     /// Response output parts: body | full
     #[serde(default = "Default::default")]
     pub output_parts: OutputParts,
@@ -50,17 +110,370 @@ pub(crate) struct HttpConfig {
     #[serde(default = "Default::default")]
     pub output_type: OutputType,
 
+    /// How response bodies are carried into records: text | raw | base64
+    #[serde(default = "Default::default")]
+    pub body_format: BodyFormat,
+
     #[serde(default = "Default::default")]
     pub websocket_config: Option<WebSocketConfig>,
+
+    /// Pagination settings used to follow multi-page responses when polling
+    #[serde(default = "Default::default")]
+    pub pagination: Option<PaginationConfig>,
+
+    /// Send `If-None-Match` and `If-Modified-Since` headers based on the previous response
+    /// and skip `304 Not Modified` responses
+    #[serde(default = "Default::default")]
+    pub conditional_requests: bool,
+
+    /// Skip pages whose body has the same SHA-256 hash as the same page of the previous tick
+    #[serde(default = "Default::default")]
+    pub dedup: bool,
+
+    /// JSON pointer or JSONPath of an array in the response body,
+    /// each element is produced as a separate record
+    #[serde(default = "Default::default")]
+    pub record_selector: Option<RecordSelector>,
+
+    /// Child requests sent for each item of the response, whose responses are produced instead
+    #[serde(default = "Default::default")]
+    pub fan_out: Option<FanOutConfig>,
+
+    /// Derive the key of each record from the response: pointer | header | template
+    #[serde(default = "Default::default")]
+    pub record_key: Option<RecordKeyConfig>,
+
+    /// Delays and attempts when connecting or sending a polling request fails
+    #[serde(default = "Default::default")]
+    pub retry: Option<RetryPolicy>,
+
+    /// Accepted response statuses and the action for rejected responses
+    #[serde(default = "Default::default")]
+    pub status_policy: Option<StatusPolicy>,
+
+    /// Topic receiving responses that could not be published
+    #[serde(default = "Default::default")]
+    pub dead_letter_topic: Option<String>,
+
+    /// Where polling state is saved to resume after a restart: file | topic
+    #[serde(default = "Default::default")]
+    pub checkpoint: Option<CheckpointConfig>,
 }
 
 #[connector(config, name = "websocket")]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct WebSocketConfig {
     pub(crate) subscription_message: Option<String>,
     pub(crate) subscription_messages: Option<Vec<String>>,
-    // TODO: pub(crate) max_message_size: Option<usize>,
+    /// Message sent before the subscription messages, a template rendered on every connection
+    pub(crate) auth_message: Option<SecretString>,
+    /// Message to wait for before sending the subscription messages
+    pub(crate) await_ack: Option<AckConfig>,
+    /// Largest accepted message in bytes, 64 MiB when unset
+    pub(crate) max_message_size: Option<usize>,
+    /// Largest accepted frame payload in bytes, 16 MiB when unset
+    pub(crate) max_frame_size: Option<usize>,
+    /// What happens to messages larger than max_message_size: drop | dead_letter | reconnect
+    #[serde(default = "Default::default")]
+    pub(crate) on_oversized_message: OversizedMessageAction,
     pub(crate) ping_interval_ms: Option<u64>,
+    /// Text message sent with every ping, for servers expecting application heartbeats
+    pub(crate) keepalive_message: Option<String>,
+    /// Replies to `keepalive_message`, counted as pongs instead of being produced
+    pub(crate) heartbeat_reply: Option<MessagePattern>,
+    /// Reconnect when a ping is not answered within this many milliseconds
+    pub(crate) pong_timeout_ms: Option<u64>,
+    /// Reconnect when no message is received for this many milliseconds
+    pub(crate) idle_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OversizedMessageAction {
+    /// Log the message size and reconnect
+    #[default]
+    Drop,
+    /// Send the message size to the `dead_letter_topic` and reconnect
+    DeadLetter,
+    /// Close the connection and connect again
+    Reconnect,
+}
+
+/// Acknowledgement of the `auth_message`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct AckConfig {
+    /// JSON message with `value` at `pointer`
+    #[serde(flatten)]
+    pub pattern: MessagePattern,
+    /// Time to wait for the acknowledgement before reconnecting
+    #[serde(default = "default_ack_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+/// WebSocket message matched by its text or by a value in its JSON body
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum MessagePattern {
+    /// Message with exactly this text
+    Text(String),
+    /// JSON message with `value` at `pointer`, e.g. "/status"
+    Json {
+        pointer: String,
+        value: serde_json::Value,
+    },
+}
+
+/// Endpoint of the `endpoints` list, unset settings are taken from the top level config
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct EndpointConfig {
+    /// Name tagging the records of this endpoint, used as the default record key
+    pub name: String,
+
+    pub endpoint: SecretString,
+
+    pub method: Option<String>,
+
+    /// Replace the top level headers
+    pub headers: Option<Vec<SecretString>>,
+
+    pub body: Option<String>,
+
+    #[serde(default = "Default::default", with = "humantime_serde")]
+    pub interval: Option<Duration>,
+
+    pub output_parts: Option<OutputParts>,
+
+    pub output_type: Option<OutputType>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum AuthConfig {
+    /// HTTP Basic authentication
+    Basic(BasicAuthConfig),
+    /// Static bearer token sent in the `Authorization` header
+    Bearer(BearerAuthConfig),
+    /// API key sent in a header or a query parameter
+    ApiKey(ApiKeyConfig),
+    /// Bearer token obtained from an OAuth2 token endpoint
+    #[serde(rename = "oauth2")]
+    OAuth2(OAuth2Config),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct BasicAuthConfig {
+    pub username: SecretString,
+
+    pub password: Option<SecretString>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct BearerAuthConfig {
+    pub token: SecretString,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct ApiKeyConfig {
+    /// Name of the header or query parameter carrying the key
+    pub name: String,
+
+    pub value: SecretString,
+
+    /// Where the key is sent: header | query
+    #[serde(default = "Default::default", rename = "in")]
+    pub location: ApiKeyLocation,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ApiKeyLocation {
+    #[default]
+    Header,
+    Query,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct OAuth2Config {
+    /// URL of the token endpoint
+    pub token_url: SecretString,
+
+    pub client_id: SecretString,
+
+    pub client_secret: Option<SecretString>,
+
+    /// OAuth2 grant used to obtain tokens: client_credentials | refresh_token
+    #[serde(default = "Default::default")]
+    pub grant_type: OAuth2GrantType,
+
+    /// Refresh token, required by the refresh_token grant
+    pub refresh_token: Option<SecretString>,
+
+    /// Scopes requested for the token
+    #[serde(default = "Vec::new")]
+    pub scopes: Vec<String>,
+
+    /// Audience requested for the token, used by some providers
+    pub audience: Option<String>,
+
+    /// How client credentials are sent to the token endpoint: basic | body
+    #[serde(default = "Default::default")]
+    pub client_auth: OAuth2ClientAuth,
+
+    /// Tokens are refreshed this long before they expire
+    /// Ex: '30s', '1m'
+    #[serde(with = "humantime_serde", default = "default_token_refresh_margin")]
+    pub refresh_margin: Duration,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OAuth2GrantType {
+    #[default]
+    ClientCredentials,
+    RefreshToken,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum OAuth2ClientAuth {
+    /// HTTP Basic authentication header
+    #[default]
+    Basic,
+    /// `client_id` and `client_secret` form parameters
+    Body,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum CheckpointConfig {
+    /// Local JSON file, replaced on every save
+    File { path: PathBuf },
+    /// Last record of a Fluvio topic
+    Topic { topic: String },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct PaginationConfig {
+    /// How the next page is located
+    #[serde(flatten)]
+    pub strategy: PaginationStrategy,
+
+    /// JSON pointer to the array of items in each page, e.g. "/data".
+    /// Defaults to the whole body
+    #[serde(default = "Default::default")]
+    pub items_pointer: String,
+
+    /// Produce one record per page or one record per item: page | item
+    #[serde(default = "Default::default")]
+    pub emit: PaginationEmit,
+
+    /// Upper bound of pages fetched on a single tick
+    pub max_pages: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub(crate) enum PaginationStrategy {
+    /// Follow the `rel="next"` URL of the `Link` response header
+    LinkHeader,
+    /// Follow the URL found at a JSON pointer in the response body
+    NextUrl { next_url_pointer: String },
+    /// Pass the token found at a JSON pointer as a query parameter
+    Cursor {
+        cursor_pointer: String,
+        #[serde(default = "default_cursor_param")]
+        cursor_param: String,
+    },
+    /// Increment a page number query parameter until a page has no items
+    Page {
+        #[serde(default = "default_page_param")]
+        page_param: String,
+        #[serde(default = "default_start_page")]
+        start_page: u64,
+        page_size: Option<usize>,
+    },
+    /// Advance an offset query parameter by the number of items received
+    Offset {
+        #[serde(default = "default_offset_param")]
+        offset_param: String,
+        limit_param: Option<String>,
+        limit: usize,
+    },
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PaginationEmit {
+    #[default]
+    Page,
+    Item,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct FanOutConfig {
+    /// JSON pointer or JSONPath of the parent items in the parent response body.
+    /// Defaults to the whole body, an array yields one item per element
+    #[serde(default = "Default::default")]
+    pub items: RecordSelector,
+
+    /// Child request URL, `{{ item }}` and `{{ item:<pointer> }}` render the parent item
+    pub endpoint: String,
+
+    #[serde(default = "default_http_method")]
+    pub method: String,
+
+    /// Headers added to the top level headers for child requests
+    #[serde(default = "Vec::new")]
+    pub headers: Vec<SecretString>,
+
+    pub body: Option<String>,
+
+    /// Child requests sent at the same time
+    #[serde(default = "default_fan_out_concurrency")]
+    pub max_concurrency: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RecordKeyConfig {
+    /// JSON pointer into the record body, e.g. "/id"
+    Pointer(String),
+    /// Name of a response header
+    Header(String),
+    /// Template with `{{ header:<name> }}` and `{{ body:<pointer> }}` expressions
+    Template(Template),
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MissedTick {
+    /// Send the missed ticks right away until the schedule is caught up
+    #[default]
+    Burst,
+    /// Send one tick right away and continue `interval` after it
+    Delay,
+    /// Drop the missed ticks and continue on the original schedule
+    Skip,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Compression {
+    Gzip,
+    Deflate,
+    #[serde(alias = "br")]
+    Brotli,
+    Zstd,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StreamFormat {
+    /// Records are separated by `delimiter`
+    #[default]
+    Delimited,
+    /// Records are the `data` of `text/event-stream` events
+    Sse,
 }
 
 #[derive(Debug, Default, Deserialize, Clone, Copy)]
@@ -79,6 +492,68 @@ pub(crate) enum OutputType {
     Json,
 }
 
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BodyFormat {
+    /// Decoded to UTF-8 text using the charset of the response
+    #[default]
+    Text,
+    /// Bytes passed through unchanged, only with `output_type: text`
+    Raw,
+    /// Bytes encoded as base64 text
+    Base64,
+}
+
+impl HttpConfig {
+    /// Config of every endpoint to poll, paired with the endpoint name when `endpoints` is used
+    pub(crate) fn endpoint_configs(&self) -> Result<Vec<(Option<String>, HttpConfig)>> {
+        match (&self.endpoint, self.endpoints.is_empty()) {
+            (Some(_), true) => return Ok(vec![(None, self.clone())]),
+            (None, true) => return Err(anyhow!("either endpoint or endpoints must be set")),
+            (Some(_), false) => {
+                return Err(anyhow!("endpoint and endpoints cannot be used together"))
+            }
+            (None, false) => {}
+        }
+
+        let mut names = HashSet::new();
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                if !names.insert(endpoint.name.as_str()) {
+                    return Err(anyhow!("duplicate endpoint name \"{}\"", endpoint.name));
+                }
+
+                let mut config = HttpConfig {
+                    endpoint: Some(endpoint.endpoint.clone()),
+                    endpoints: Vec::new(),
+                    ..self.clone()
+                };
+                if let Some(ref method) = endpoint.method {
+                    config.method = method.clone();
+                }
+                if let Some(ref headers) = endpoint.headers {
+                    config.headers = headers.clone();
+                }
+                if endpoint.body.is_some() {
+                    config.body = endpoint.body.clone();
+                }
+                if let Some(interval) = endpoint.interval {
+                    config.interval = interval;
+                }
+                if let Some(output_parts) = endpoint.output_parts {
+                    config.output_parts = output_parts;
+                }
+                if let Some(output_type) = endpoint.output_type {
+                    config.output_type = output_type;
+                }
+
+                Ok((Some(endpoint.name.clone()), config))
+            })
+            .collect()
+    }
+}
+
 fn default_user_agent() -> String {
     DEFAULT_USER_AGENT.into()
 }
@@ -94,3 +569,137 @@ fn default_interval() -> Duration {
 fn default_delimiter() -> String {
     DEFAULT_DELIMITER.into()
 }
+
+fn default_page_param() -> String {
+    DEFAULT_PAGE_PARAM.into()
+}
+
+fn default_start_page() -> u64 {
+    DEFAULT_START_PAGE
+}
+
+fn default_offset_param() -> String {
+    DEFAULT_OFFSET_PARAM.into()
+}
+
+fn default_cursor_param() -> String {
+    DEFAULT_CURSOR_PARAM.into()
+}
+
+fn default_token_refresh_margin() -> Duration {
+    DEFAULT_TOKEN_REFRESH_MARGIN
+}
+
+fn default_fan_out_concurrency() -> usize {
+    DEFAULT_FAN_OUT_CONCURRENCY
+}
+
+fn default_max_in_flight() -> usize {
+    DEFAULT_MAX_IN_FLIGHT
+}
+
+fn default_ack_timeout_ms() -> u64 {
+    DEFAULT_ACK_TIMEOUT_MS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_configs_override_top_level() {
+        let config: HttpConfig = serde_json::from_str(
+            r#"{
+                "interval": "5s",
+                "headers": ["Accept: application/json"],
+                "endpoints": [
+                    {"name": "orders", "endpoint": "http://localhost/orders"},
+                    {
+                        "name": "users",
+                        "endpoint": "http://localhost/users",
+                        "method": "POST",
+                        "headers": [],
+                        "interval": "1m",
+                        "output_type": "json"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let endpoints = config.endpoint_configs().unwrap();
+        assert_eq!(endpoints.len(), 2);
+
+        let (name, orders) = &endpoints[0];
+        assert_eq!(name.as_deref(), Some("orders"));
+        assert_eq!(orders.method, "GET");
+        assert_eq!(orders.interval, Duration::from_secs(5));
+        assert_eq!(orders.headers.len(), 1);
+
+        let (name, users) = &endpoints[1];
+        assert_eq!(name.as_deref(), Some("users"));
+        assert_eq!(users.method, "POST");
+        assert_eq!(users.interval, Duration::from_secs(60));
+        assert!(users.headers.is_empty());
+        assert!(matches!(users.output_type, OutputType::Json));
+        assert!(users.endpoints.is_empty());
+    }
+
+    #[test]
+    fn test_polling_overlap_settings() {
+        let config: HttpConfig = serde_json::from_str(
+            r#"{"endpoint": "http://localhost", "max_in_flight": 2, "missed_tick": "skip", "timeout": "5s"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.max_in_flight, 2);
+        assert_eq!(config.missed_tick, MissedTick::Skip);
+        assert_eq!(config.timeout, Some(Duration::from_secs(5)));
+
+        let config: HttpConfig =
+            serde_json::from_str(r#"{"endpoint": "http://localhost"}"#).unwrap();
+        assert_eq!(config.max_in_flight, DEFAULT_MAX_IN_FLIGHT);
+        assert_eq!(config.missed_tick, MissedTick::Burst);
+        assert_eq!(config.timeout, None);
+    }
+
+    #[test]
+    fn test_compression_encodings() {
+        let config: HttpConfig = serde_json::from_str(
+            r#"{"endpoint": "http://localhost", "compression": ["gzip", "br", "zstd"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.compression,
+            vec![Compression::Gzip, Compression::Brotli, Compression::Zstd]
+        );
+
+        assert!(serde_json::from_str::<HttpConfig>(
+            r#"{"endpoint": "http://localhost", "compression": ["lzma"]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_endpoint_configs_validation() {
+        let parse = |json: &str| serde_json::from_str::<HttpConfig>(json).unwrap();
+
+        assert!(parse(r#"{}"#).endpoint_configs().is_err());
+        assert!(parse(
+            r#"{"endpoint": "http://localhost", "endpoints": [{"name": "a", "endpoint": "http://localhost"}]}"#
+        )
+        .endpoint_configs()
+        .is_err());
+        assert!(parse(
+            r#"{"endpoints": [{"name": "a", "endpoint": "http://a"}, {"name": "a", "endpoint": "http://b"}]}"#
+        )
+        .endpoint_configs()
+        .is_err());
+        assert_eq!(
+            parse(r#"{"endpoint": "http://localhost"}"#)
+                .endpoint_configs()
+                .unwrap()[0]
+                .0,
+            None
+        );
+    }
+}
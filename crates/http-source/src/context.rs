@@ -0,0 +1,68 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use crate::{
+    auth::Authenticator, checkpoint::CheckpointStore, config::HttpConfig,
+    dead_letter::DeadLetterQueue, poll_state::PollState, request_template::RequestTemplate,
+    sse::SseState,
+};
+
+/// State shared by the sources and kept across reconnects
+#[derive(Clone)]
+pub(crate) struct SourceContext {
+    /// Name of the endpoint when `endpoints` is used
+    pub endpoint_name: Option<String>,
+    pub sse_state: SseState,
+    pub auth: Option<Authenticator>,
+    pub request: RequestTemplate,
+    /// Template of the `fan_out` child requests
+    pub fan_out_request: Option<RequestTemplate>,
+    pub dead_letter: Option<DeadLetterQueue>,
+    pub poll_state: Arc<Mutex<PollState>>,
+    pub checkpoints: Option<Arc<CheckpointStore>>,
+}
+
+impl SourceContext {
+    /// Creates the context of one endpoint, `auth` and `dead_letter` are shared by all endpoints
+    pub(crate) async fn new(
+        config: &HttpConfig,
+        endpoint_name: Option<String>,
+        auth: Option<Authenticator>,
+        dead_letter: Option<DeadLetterQueue>,
+    ) -> Result<Self> {
+        let request = RequestTemplate::new(config)?;
+        let fan_out_request = config
+            .fan_out
+            .as_ref()
+            .map(|fan_out| request.fan_out(fan_out))
+            .transpose()?;
+        let checkpoints = match config.checkpoint {
+            Some(ref checkpoint) => {
+                Some(CheckpointStore::new(checkpoint, endpoint_name.as_deref()).await?)
+            }
+            None => None,
+        };
+
+        let mut poll_state = PollState::default();
+        if let Some(ref checkpoints) = checkpoints {
+            if let Some(checkpoint) = checkpoints.load().await? {
+                if let Some(last_success_time) = checkpoint.last_success_time {
+                    request.restore(last_success_time);
+                }
+                poll_state = checkpoint.poll;
+            }
+        }
+
+        Ok(Self {
+            endpoint_name,
+            sse_state: Default::default(),
+            auth,
+            request,
+            fan_out_request,
+            dead_letter,
+            poll_state: Arc::new(Mutex::new(poll_state)),
+            checkpoints: checkpoints.map(Arc::new),
+        })
+    }
+}
@@ -0,0 +1,248 @@
+use std::{sync::Arc, time::SystemTime};
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use fluvio::{RecordKey, TopicProducerPool};
+use fluvio_connector_common::tracing::{error, warn};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::formatter::HttpResponseMetadata;
+
+/// Producer for the `dead_letter_topic`
+#[derive(Clone)]
+pub(crate) struct DeadLetterQueue {
+    topic: String,
+    producer: Arc<TopicProducerPool>,
+}
+
+/// Envelope describing a request or record that could not be published
+#[derive(Debug, Serialize)]
+pub(crate) struct DeadLetter {
+    pub kind: DeadLetterKind,
+    pub endpoint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub headers: Map<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_encoding: Option<BodyEncoding>,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DeadLetterKind {
+    /// The response status was rejected by the status policy
+    RejectedStatus,
+    /// The request could not be sent or its response could not be read
+    RequestFailed,
+    /// A record could not be formatted
+    FormatFailed,
+    /// A WebSocket message exceeded `max_message_size` or `max_frame_size`
+    OversizedMessage,
+}
+
+/// How `body` is rendered in the envelope
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BodyEncoding {
+    /// The body is valid UTF-8 and is kept as is
+    Text,
+    /// The body is binary and is base64 encoded
+    Base64,
+}
+
+impl DeadLetter {
+    /// Describes a response, with the raw body or chunk that was not published
+    pub(crate) fn new(
+        kind: DeadLetterKind,
+        endpoint: impl Into<String>,
+        metadata: &HttpResponseMetadata,
+        body: &[u8],
+    ) -> Self {
+        let headers = metadata
+            .headers
+            .iter()
+            .flatten()
+            .map(|header| (header.name.clone(), Value::String(header.value.clone())))
+            .collect();
+        let (body, body_encoding) = match std::str::from_utf8(body) {
+            Ok(text) => (text.to_owned(), BodyEncoding::Text),
+            Err(_) => (STANDARD.encode(body), BodyEncoding::Base64),
+        };
+
+        Self {
+            kind,
+            endpoint: endpoint.into(),
+            error: None,
+            status: metadata.status_code,
+            headers,
+            body: Some(body),
+            body_encoding: Some(body_encoding),
+            timestamp: timestamp(),
+        }
+    }
+
+    /// Describes a request that failed before a response was received
+    pub(crate) fn request_failed(endpoint: impl Into<String>, err: &anyhow::Error) -> Self {
+        Self {
+            kind: DeadLetterKind::RequestFailed,
+            endpoint: endpoint.into(),
+            error: Some(format!("{err:#}")),
+            status: None,
+            headers: Map::new(),
+            body: None,
+            body_encoding: None,
+            timestamp: timestamp(),
+        }
+    }
+
+    /// Describes a WebSocket message that was skipped because of its size
+    pub(crate) fn oversized_message(
+        endpoint: impl Into<String>,
+        metadata: &HttpResponseMetadata,
+        size: usize,
+        max_size: usize,
+    ) -> Self {
+        Self {
+            body: None,
+            body_encoding: None,
+            error: Some(format!(
+                "message of {size} bytes exceeds the size limit of {max_size} bytes"
+            )),
+            ..Self::new(DeadLetterKind::OversizedMessage, endpoint, metadata, &[])
+        }
+    }
+
+    pub(crate) fn with_error(mut self, err: &anyhow::Error) -> Self {
+        self.error = Some(format!("{err:#}"));
+        self
+    }
+}
+
+fn timestamp() -> String {
+    humantime::format_rfc3339_millis(SystemTime::now()).to_string()
+}
+
+impl DeadLetterQueue {
+    pub(crate) async fn new(topic: &str) -> Result<Self> {
+        let producer = fluvio::producer(topic)
+            .await
+            .with_context(|| format!("unable to create producer for dead letter topic {topic}"))?;
+
+        Ok(Self {
+            topic: topic.to_string(),
+            producer: Arc::new(producer),
+        })
+    }
+
+    /// Sends a dead letter, logging instead of failing so the source keeps running
+    pub(crate) async fn send(&self, letter: DeadLetter) {
+        warn!(
+            kind = ?letter.kind,
+            endpoint = letter.endpoint,
+            "Forwarding to dead letter topic {}",
+            self.topic
+        );
+
+        let result = match serde_json::to_string(&letter) {
+            Ok(json) => self.producer.send(RecordKey::NULL, json).await.map(|_| ()),
+            Err(err) => Err(err.into()),
+        };
+
+        if let Err(err) = result {
+            error!(
+                "Unable to send to dead letter topic {}: {err:?}",
+                self.topic
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::HttpHeader;
+
+    #[test]
+    fn test_dead_letter_envelope() {
+        let metadata = HttpResponseMetadata {
+            status_code: Some(503),
+            headers: Some(vec![HttpHeader {
+                name: "retry-after".into(),
+                value: "5".into(),
+            }]),
+            ..Default::default()
+        };
+
+        let letter = DeadLetter::new(
+            DeadLetterKind::RejectedStatus,
+            "http://localhost/get",
+            &metadata,
+            b"unavailable",
+        );
+        let json: Value = serde_json::to_value(&letter).unwrap();
+
+        assert_eq!(json["kind"], "rejected_status");
+        assert_eq!(json["endpoint"], "http://localhost/get");
+        assert_eq!(json["status"], 503);
+        assert_eq!(json["headers"]["retry-after"], "5");
+        assert_eq!(json["body"], "unavailable");
+        assert_eq!(json["body_encoding"], "text");
+        assert!(json["timestamp"].is_string());
+        assert!(json.get("error").is_none());
+    }
+
+    #[test]
+    fn test_oversized_message_envelope() {
+        let letter =
+            DeadLetter::oversized_message("ws://localhost/websocket", &Default::default(), 20, 10);
+        let json: Value = serde_json::to_value(&letter).unwrap();
+
+        assert_eq!(json["kind"], "oversized_message");
+        assert_eq!(
+            json["error"],
+            "message of 20 bytes exceeds the size limit of 10 bytes"
+        );
+        assert!(json.get("body").is_none());
+        assert!(json.get("body_encoding").is_none());
+    }
+
+    #[test]
+    fn test_binary_body_is_base64_encoded() {
+        let body = [0x08, 0x96, 0x01, 0xff];
+
+        let letter = DeadLetter::new(
+            DeadLetterKind::FormatFailed,
+            "http://localhost/get",
+            &Default::default(),
+            &body,
+        );
+        let json: Value = serde_json::to_value(&letter).unwrap();
+
+        assert_eq!(json["body"], "CJYB/w==");
+        assert_eq!(json["body_encoding"], "base64");
+        assert_eq!(
+            STANDARD.decode(json["body"].as_str().unwrap()).unwrap(),
+            body
+        );
+    }
+
+    #[test]
+    fn test_request_failed_envelope() {
+        let err = anyhow::anyhow!("connection refused").context("send request");
+
+        let letter = DeadLetter::request_failed("http://localhost/get", &err);
+        let json: Value = serde_json::to_value(&letter).unwrap();
+
+        assert_eq!(json["kind"], "request_failed");
+        assert_eq!(json["error"], "send request: connection refused");
+        assert!(json.get("status").is_none());
+        assert!(json.get("body").is_none());
+    }
+}
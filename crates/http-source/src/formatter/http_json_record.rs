@@ -1,19 +1,32 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use serde::Serialize;
+use serde_json::Value;
 use std::collections::{btree_map::Entry, BTreeMap};
 
+use crate::config::BodyFormat;
+
 use super::{
     http_response_metadata::{HttpHeader, HttpResponseMetadata},
-    http_response_record::HttpResponseRecord,
+    http_response_record::{EventMetadata, FrameMetadata, FrameOpcode, HttpResponseRecord},
 };
 
 #[derive(Debug, Serialize, PartialEq, Eq)]
 pub(crate) struct HttpJsonRecord {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    endpoint: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     status: Option<HttpJsonStatus>,
     #[serde(skip_serializing_if = "Option::is_none")]
     header: Option<BTreeMap<String, JsonHeadersValue>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<HttpJsonEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame: Option<HttpJsonFrame>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<Value>,
 }
 
 #[derive(Debug, Serialize, PartialEq, Eq)]
@@ -26,17 +39,65 @@ struct HttpJsonStatus {
     string: Option<&'static str>,
 }
 
-impl From<&HttpResponseRecord> for HttpJsonRecord {
-    fn from(resp_record: &HttpResponseRecord) -> Self {
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct HttpJsonEvent {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+}
+
+impl From<&EventMetadata> for HttpJsonEvent {
+    fn from(event: &EventMetadata) -> Self {
+        Self {
+            name: event.name.clone(),
+            id: event.id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct HttpJsonFrame {
+    received_at: String,
+    opcode: &'static str,
+    sequence: u64,
+    connection_id: String,
+}
+
+impl From<&FrameMetadata> for HttpJsonFrame {
+    fn from(frame: &FrameMetadata) -> Self {
+        Self {
+            received_at: humantime::format_rfc3339_millis(frame.received_at).to_string(),
+            opcode: match frame.opcode {
+                FrameOpcode::Text => "text",
+                FrameOpcode::Binary => "binary",
+            },
+            sequence: frame.sequence,
+            connection_id: frame.connection_id.clone(),
+        }
+    }
+}
+
+impl HttpJsonRecord {
+    /// JSON representation of a record, with the body as UTF-8 text or base64
+    pub(crate) fn new(resp_record: &HttpResponseRecord, body_format: BodyFormat) -> Result<Self> {
         let HttpResponseMetadata {
             version,
             status_code,
             status_string,
             headers,
         } = &resp_record.metadata;
-        let body = &resp_record.body;
+        let body = match (&resp_record.body, body_format) {
+            (Some(body), BodyFormat::Base64) => Some(STANDARD.encode(body)),
+            (Some(body), BodyFormat::Text | BodyFormat::Raw) => Some(
+                String::from_utf8(body.to_vec())
+                    .map_err(|_| anyhow!("body is not valid UTF-8, use body_format: base64"))?,
+            ),
+            (None, _) => None,
+        };
 
         let header = headers.clone().map(headers_to_json);
+        let event = resp_record.event.as_ref().map(HttpJsonEvent::from);
+        let frame = resp_record.frame.as_ref().map(HttpJsonFrame::from);
 
         let status = match (&version, &status_code, &status_string) {
             (None, None, None) => None,
@@ -47,11 +108,20 @@ impl From<&HttpResponseRecord> for HttpJsonRecord {
             }),
         };
 
-        HttpJsonRecord {
+        Ok(HttpJsonRecord {
+            endpoint: None,
             status,
             header,
-            body: body.clone(),
-        }
+            body,
+            event,
+            frame,
+            parent: resp_record.parent.clone(),
+        })
+    }
+
+    pub(crate) fn with_endpoint(mut self, endpoint: Option<String>) -> Self {
+        self.endpoint = endpoint;
+        self
     }
 }
 
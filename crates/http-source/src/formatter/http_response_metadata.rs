@@ -1,4 +1,5 @@
 use anyhow::Result;
+use http::{HeaderMap, StatusCode, Version};
 use reqwest::Response;
 
 #[derive(Debug, Default, Clone)]
@@ -17,12 +18,20 @@ pub(crate) struct HttpHeader {
 
 impl HttpResponseMetadata {
     pub fn new(response: &Response) -> Result<Self> {
-        let status_code = Some(response.status().as_u16());
-        let status_string = response.status().canonical_reason();
-        let version = Some(format!("{:?}", response.version()));
+        Self::from_parts(response.version(), response.status(), response.headers())
+    }
+
+    /// Metadata of a response received outside of reqwest, e.g. a WebSocket handshake
+    pub fn from_http_response<T>(response: &http::Response<T>) -> Result<Self> {
+        Self::from_parts(response.version(), response.status(), response.headers())
+    }
+
+    fn from_parts(version: Version, status: StatusCode, headers: &HeaderMap) -> Result<Self> {
+        let status_code = Some(status.as_u16());
+        let status_string = status.canonical_reason();
+        let version = Some(format!("{:?}", version));
         let headers = Some(
-            response
-                .headers()
+            headers
                 .iter()
                 .map(|(key, value)| {
                     value.to_str().map(|value| HttpHeader {
@@ -40,4 +49,13 @@ impl HttpResponseMetadata {
             headers,
         })
     }
+
+    /// Returns the first value of the header `name`, compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .as_ref()?
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(name))
+            .map(|header| header.value.as_str())
+    }
 }
@@ -1,16 +1,67 @@
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use serde_json::Value;
+
 use super::http_response_metadata::HttpResponseMetadata;
 
 #[derive(Debug, Default, Clone)]
 pub(crate) struct HttpResponseRecord {
     pub metadata: HttpResponseMetadata,
-    pub body: Option<String>,
+    pub body: Option<Bytes>,
+    pub event: Option<EventMetadata>,
+    pub frame: Option<FrameMetadata>,
+    /// Parent item of a `fan_out` child response
+    pub parent: Option<Value>,
+}
+
+/// Server-Sent Events fields of the event a record was read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EventMetadata {
+    pub name: String,
+    pub id: Option<String>,
+}
+
+/// WebSocket message a record was read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FrameMetadata {
+    pub received_at: SystemTime,
+    pub opcode: FrameOpcode,
+    /// Position of the message on its connection, starting at 1
+    pub sequence: u64,
+    /// Random id of the connection, new on every reconnect
+    pub connection_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FrameOpcode {
+    Text,
+    Binary,
 }
 
 impl HttpResponseRecord {
-    pub fn new(response_metadata: HttpResponseMetadata, record_body: String) -> Self {
+    pub fn new(response_metadata: HttpResponseMetadata, record_body: impl Into<Bytes>) -> Self {
         Self {
             metadata: response_metadata,
-            body: Some(record_body),
+            body: Some(record_body.into()),
+            event: None,
+            frame: None,
+            parent: None,
         }
     }
+
+    pub fn with_event(mut self, event: Option<EventMetadata>) -> Self {
+        self.event = event;
+        self
+    }
+
+    pub fn with_frame(mut self, frame: Option<FrameMetadata>) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    pub fn with_parent(mut self, parent: Option<Value>) -> Self {
+        self.parent = parent;
+        self
+    }
 }
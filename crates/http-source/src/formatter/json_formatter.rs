@@ -1,22 +1,111 @@
-use crate::config::OutputParts;
+use crate::config::{BodyFormat, OutputParts};
 
 use super::{
     http_json_record::HttpJsonRecord, http_response_record::HttpResponseRecord, Formatter,
 };
 
+/// Formats records as JSON, tagged with the endpoint name when set
 #[derive(Clone)]
-pub(crate) struct JsonFormatter(pub OutputParts);
+pub(crate) struct JsonFormatter(pub OutputParts, pub BodyFormat, pub Option<String>);
 
 impl Formatter for JsonFormatter {
-    fn to_string(&self, record: &HttpResponseRecord) -> anyhow::Result<String> {
+    fn format(&self, record: &HttpResponseRecord) -> anyhow::Result<Vec<u8>> {
         let json_record = match self.0 {
-            OutputParts::Body => HttpJsonRecord::from(&HttpResponseRecord {
-                body: record.body.clone(),
-                ..Default::default()
-            }),
-            OutputParts::Full => HttpJsonRecord::from(record),
+            OutputParts::Body => HttpJsonRecord::new(
+                &HttpResponseRecord {
+                    body: record.body.clone(),
+                    event: record.event.clone(),
+                    frame: record.frame.clone(),
+                    parent: record.parent.clone(),
+                    ..Default::default()
+                },
+                self.1,
+            )?,
+            OutputParts::Full => HttpJsonRecord::new(record, self.1)?,
         };
 
-        Ok(serde_json::to_string(&json_record)?)
+        Ok(serde_json::to_vec(
+            &json_record.with_endpoint(self.2.clone()),
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_name_in_output() {
+        let record = HttpResponseRecord {
+            body: Some("hello".into()),
+            ..Default::default()
+        };
+
+        let tagged = JsonFormatter(OutputParts::Body, BodyFormat::Text, Some("orders".into()));
+        assert_eq!(
+            tagged.format(&record).unwrap(),
+            br#"{"endpoint":"orders","body":"hello"}"#
+        );
+
+        let untagged = JsonFormatter(OutputParts::Body, BodyFormat::Text, None);
+        assert_eq!(untagged.format(&record).unwrap(), br#"{"body":"hello"}"#);
+    }
+
+    #[test]
+    fn test_parent_item_in_output() {
+        let record = HttpResponseRecord::new(Default::default(), "[]")
+            .with_parent(Some(serde_json::json!({"id": 7})));
+
+        let formatter = JsonFormatter(OutputParts::Body, BodyFormat::Text, None);
+        assert_eq!(
+            formatter.format(&record).unwrap(),
+            br#"{"body":"[]","parent":{"id":7}}"#
+        );
+    }
+
+    #[test]
+    fn test_websocket_frame_in_output() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        use crate::formatter::{FrameMetadata, FrameOpcode, HttpResponseMetadata};
+
+        let metadata = HttpResponseMetadata {
+            version: Some("HTTP/1.1".into()),
+            status_code: Some(101),
+            status_string: Some("Switching Protocols"),
+            headers: None,
+        };
+        let record = HttpResponseRecord::new(metadata, "hello").with_frame(Some(FrameMetadata {
+            received_at: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            opcode: FrameOpcode::Text,
+            sequence: 3,
+            connection_id: "9f86d081884c7d65".into(),
+        }));
+        let frame = r#""frame":{"received_at":"2023-11-14T22:13:20.123Z","opcode":"text","sequence":3,"connection_id":"9f86d081884c7d65"}"#;
+
+        let body = JsonFormatter(OutputParts::Body, BodyFormat::Text, None);
+        assert_eq!(
+            String::from_utf8(body.format(&record).unwrap()).unwrap(),
+            format!(r#"{{"body":"hello",{frame}}}"#)
+        );
+
+        let full = JsonFormatter(OutputParts::Full, BodyFormat::Text, None);
+        assert_eq!(
+            String::from_utf8(full.format(&record).unwrap()).unwrap(),
+            format!(
+                r#"{{"status":{{"version":"HTTP/1.1","code":101,"string":"Switching Protocols"}},"body":"hello",{frame}}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_binary_body() {
+        let record = HttpResponseRecord::new(Default::default(), vec![0x08, 0x96, 0x01, 0xff]);
+
+        let base64 = JsonFormatter(OutputParts::Body, BodyFormat::Base64, None);
+        assert_eq!(base64.format(&record).unwrap(), br#"{"body":"CJYB/w=="}"#);
+
+        let text = JsonFormatter(OutputParts::Body, BodyFormat::Text, None);
+        assert!(text.format(&record).is_err());
     }
 }
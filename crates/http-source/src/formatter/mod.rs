@@ -4,25 +4,41 @@ mod http_response_record;
 mod json_formatter;
 mod text_formatter;
 
-use std::sync::Arc;
+use std::{borrow::Cow, sync::Arc};
 
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+#[cfg(test)]
+pub(crate) use http_response_metadata::HttpHeader;
 pub(crate) use http_response_metadata::HttpResponseMetadata;
-pub(crate) use http_response_record::HttpResponseRecord;
+pub(crate) use http_response_record::{
+    EventMetadata, FrameMetadata, FrameOpcode, HttpResponseRecord,
+};
 use json_formatter::JsonFormatter;
 use text_formatter::TextFormatter;
 
-use crate::config::{OutputParts, OutputType};
+use crate::config::{BodyFormat, OutputParts, OutputType};
 
 pub(crate) trait Formatter {
-    fn to_string(&self, response: &HttpResponseRecord) -> anyhow::Result<String>;
+    fn format(&self, response: &HttpResponseRecord) -> anyhow::Result<Vec<u8>>;
 }
 
 pub(crate) fn formatter(
     output_type: OutputType,
     output_parts: OutputParts,
+    body_format: BodyFormat,
+    endpoint_name: Option<String>,
 ) -> Arc<dyn Formatter + Sync + Send> {
     match output_type {
-        OutputType::Text => Arc::new(TextFormatter(output_parts)),
-        OutputType::Json => Arc::new(JsonFormatter(output_parts)),
+        OutputType::Text => Arc::new(TextFormatter(output_parts, body_format)),
+        OutputType::Json => Arc::new(JsonFormatter(output_parts, body_format, endpoint_name)),
+    }
+}
+
+/// Body encoded as base64 text with `body_format: base64`, unchanged otherwise
+pub(crate) fn encode_body(body: &[u8], body_format: BodyFormat) -> Cow<'_, [u8]> {
+    match body_format {
+        BodyFormat::Base64 => Cow::Owned(STANDARD.encode(body).into_bytes()),
+        BodyFormat::Text | BodyFormat::Raw => Cow::Borrowed(body),
     }
 }
@@ -1,12 +1,14 @@
-use crate::config::OutputParts;
+use crate::config::{BodyFormat, OutputParts};
 
-use super::{http_response_record::HttpResponseRecord, Formatter, HttpResponseMetadata};
+use super::{
+    encode_body, http_response_record::HttpResponseRecord, Formatter, HttpResponseMetadata,
+};
 
 #[derive(Clone)]
-pub(crate) struct TextFormatter(pub OutputParts);
+pub(crate) struct TextFormatter(pub OutputParts, pub BodyFormat);
 
 impl Formatter for TextFormatter {
-    fn to_string(&self, record: &HttpResponseRecord) -> anyhow::Result<String> {
+    fn format(&self, record: &HttpResponseRecord) -> anyhow::Result<Vec<u8>> {
         let HttpResponseMetadata {
             version,
             status_code,
@@ -40,11 +42,33 @@ impl Formatter for TextFormatter {
                 record_out_parts.push(String::from(""));
             }
         };
-        // Body with an empty line between
+
+        let mut record_out = record_out_parts.join("\n").into_bytes();
+        // Body bytes are appended as they are, so binary bodies pass through unchanged
         if let Some(body) = body {
-            record_out_parts.push(body.clone());
+            if !record_out.is_empty() {
+                record_out.push(b'\n');
+            }
+            record_out.extend_from_slice(&encode_body(body, self.1));
         }
 
-        Ok(record_out_parts.join("\n"))
+        Ok(record_out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_body() {
+        let body = vec![0x08, 0x96, 0x01, 0xff];
+        let record = HttpResponseRecord::new(Default::default(), body.clone());
+
+        let raw = TextFormatter(OutputParts::Body, BodyFormat::Raw);
+        assert_eq!(raw.format(&record).unwrap(), body);
+
+        let base64 = TextFormatter(OutputParts::Body, BodyFormat::Base64);
+        assert_eq!(base64.format(&record).unwrap(), b"CJYB/w==");
     }
 }
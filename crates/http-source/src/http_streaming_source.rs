@@ -1,76 +1,207 @@
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use encoding_rs::{Encoding, UTF_8};
 use fluvio::Offset;
 use fluvio_connector_common::{
-    tracing::{error, warn},
+    tracing::{debug, error, warn},
     Source,
 };
-use futures::{stream::BoxStream, stream::LocalBoxStream, StreamExt};
-use reqwest::{Client, RequestBuilder, Url};
-use std::sync::Arc;
+use futures::{stream::BoxStream, stream::LocalBoxStream, StreamExt, TryStreamExt};
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Client, Request, StatusCode,
+};
+use std::{sync::Arc, time::SystemTime};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 use crate::{
-    config::HttpConfig,
-    formatter::{formatter, Formatter, HttpResponseMetadata, HttpResponseRecord},
+    auth::Authenticator,
+    client,
+    config::{BodyFormat, HttpConfig, RecordKeyConfig, StreamFormat},
+    context::SourceContext,
+    dead_letter::{DeadLetter, DeadLetterKind, DeadLetterQueue},
+    formatter::{formatter, EventMetadata, Formatter, HttpResponseMetadata, HttpResponseRecord},
+    record::SourceRecord,
+    request_template::{RequestTemplate, TemplateVars},
+    sse::{SseParser, SseState},
+    status_policy::{retry_after, RejectedStatus, StatusAction, StatusPolicy},
 };
 
+const LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+
 pub(crate) struct HttpStreamingSource {
-    request: RequestBuilder,
+    client: Client,
+    request: Request,
     delimiter: Vec<u8>,
     formatter: Arc<dyn Formatter + Sync + Send>,
+    stream_format: StreamFormat,
+    body_format: BodyFormat,
+    sse_metadata: bool,
+    sse_state: SseState,
+    record_key: Option<RecordKeyConfig>,
+    auth: Option<Authenticator>,
+    status_policy: Option<StatusPolicy>,
+    dead_letter: Option<DeadLetterQueue>,
+    template: RequestTemplate,
+    vars: TemplateVars,
+}
+
+/// A record read from the response stream, before formatting
+#[derive(Debug)]
+struct StreamRecord {
+    body: Bytes,
+    event: Option<EventMetadata>,
+}
+
+impl From<Bytes> for StreamRecord {
+    fn from(body: Bytes) -> Self {
+        Self { body, event: None }
+    }
 }
 
 #[async_trait]
-impl<'a> Source<'a, String> for HttpStreamingSource {
-    async fn connect(mut self, _offset: Option<Offset>) -> Result<LocalBoxStream<'a, String>> {
-        let request = self
+impl<'a> Source<'a, Result<SourceRecord>> for HttpStreamingSource {
+    async fn connect(
+        mut self,
+        _offset: Option<Offset>,
+    ) -> Result<LocalBoxStream<'a, Result<SourceRecord>>> {
+        let mut request = self
             .request
             .try_clone()
             .context("request must be cloneable")?;
 
-        let response = request.send().await.context("send request")?;
+        if let Some(ref auth) = self.auth {
+            auth.authorize(&mut request)
+                .await
+                .context("authorize request")?;
+        }
+
+        // the URL is left out of errors since it may carry an `api_key` query parameter
+        let response = match self
+            .client
+            .execute(request)
+            .await
+            .map_err(reqwest::Error::without_url)
+            .context("send request")
+        {
+            Ok(response) => response,
+            Err(err) => {
+                if let Some(ref dead_letter) = self.dead_letter {
+                    let endpoint = self.request.url().to_string();
+                    dead_letter
+                        .send(DeadLetter::request_failed(endpoint, &err))
+                        .await;
+                }
+                return Err(err);
+            }
+        };
+
+        if response.status() == StatusCode::UNAUTHORIZED {
+            if let Some(ref auth) = self.auth {
+                auth.invalidate().await;
+                return Err(anyhow!("credentials rejected by the server"));
+            }
+        }
 
         let response_metadata = HttpResponseMetadata::new(&response)?;
-        let encoding = transfer_encoding(&response);
+        let status = response.status().as_u16();
+        if let Some(action) = self
+            .status_policy
+            .as_ref()
+            .and_then(|policy| policy.check(status))
+        {
+            let endpoint = self.request.url().to_string();
+            match action {
+                StatusAction::Drop => {}
+                StatusAction::Retry => {
+                    if let Some(wait) = retry_after(&response_metadata, SystemTime::now()) {
+                        debug!(
+                            "Waiting {} before reconnecting as requested by the server",
+                            humantime::format_duration(wait)
+                        );
+                        async_std::task::sleep(wait).await;
+                    }
+                }
+                StatusAction::DeadLetter => {
+                    let body = response.bytes().await.unwrap_or_default();
+                    if let Some(ref dead_letter) = self.dead_letter {
+                        dead_letter
+                            .send(DeadLetter::new(
+                                DeadLetterKind::RejectedStatus,
+                                endpoint.clone(),
+                                &response_metadata,
+                                &body,
+                            ))
+                            .await;
+                    }
+                }
+                StatusAction::Fail => return Err(RejectedStatus { status, endpoint }.into()),
+            }
 
-        Ok(self.record_stream(response, response_metadata, encoding))
+            return Err(anyhow!(
+                "{endpoint} responded with rejected status {status}"
+            ));
+        }
+
+        self.template.succeeded(&self.vars);
+        // raw and base64 bodies keep the bytes of the response
+        let encoding = (self.body_format == BodyFormat::Text).then(|| transfer_encoding(&response));
+
+        Ok(self
+            .record_stream(response, response_metadata, encoding)
+            .map(Ok)
+            .boxed_local())
     }
 }
 
 impl HttpStreamingSource {
-    pub(crate) fn new(config: &HttpConfig) -> Result<Self> {
-        let client = Client::new();
-        let method = config.method.parse()?;
-        let url = Url::parse(&config.endpoint.resolve()?).context("parse http endpoint")?;
-        let mut request = client.request(method, url);
-
-        request = request.header(reqwest::header::USER_AGENT, config.user_agent.clone());
-        let headers = config
-            .headers
-            .iter()
-            .map(|h| h.resolve().unwrap_or_default())
-            .collect::<Vec<_>>();
-
-        for (key, value) in headers.iter().flat_map(|h| h.split_once(':')) {
-            request = request.header(key, value);
-        }
-
-        if let Some(ref body) = config.body {
-            request = request.body(body.clone());
+    pub(crate) fn new(config: &HttpConfig, context: &SourceContext) -> Result<Self> {
+        let client = client::builder(config)?
+            .build()
+            .context("unable to build http client")?;
+        let vars = context.request.tick();
+        let mut request = context.request.build(&client, &vars)?;
+
+        if config.stream_format == StreamFormat::Sse {
+            if let Some(last_event_id) = context.sse_state.last_event_id() {
+                // ids may hold any UTF-8 text, which is not always a valid header value
+                match HeaderValue::from_str(&last_event_id) {
+                    Ok(value) => {
+                        request.headers_mut().insert(LAST_EVENT_ID, value);
+                    }
+                    Err(_) => warn!(
+                        "Last event id {last_event_id:?} is not a valid header value, reconnecting without Last-Event-ID"
+                    ),
+                }
+            }
         }
 
         let delimiter = config.delimiter.as_bytes().to_vec();
 
-        let formatter = formatter(config.output_type, config.output_parts);
+        let formatter = formatter(
+            config.output_type,
+            config.output_parts,
+            config.body_format,
+            context.endpoint_name.clone(),
+        );
 
         Ok(Self {
             delimiter,
+            client,
             request,
             formatter,
+            stream_format: config.stream_format,
+            body_format: config.body_format,
+            sse_metadata: config.sse_metadata,
+            sse_state: context.sse_state.clone(),
+            record_key: config.record_key.clone(),
+            auth: context.auth.clone(),
+            status_policy: config.status_policy.clone(),
+            dead_letter: context.dead_letter.clone(),
+            template: context.request.clone(),
+            vars,
         })
     }
 
@@ -78,25 +209,73 @@ impl HttpStreamingSource {
         self,
         response: reqwest::Response,
         response_metadata: HttpResponseMetadata,
-        encoding: &'static Encoding,
-    ) -> LocalBoxStream<'static, String> {
-        let (tx1, rx1) = mpsc::unbounded_channel();
-
-        tokio::spawn(async move {
-            read_http_stream(
-                response.bytes_stream().boxed(),
-                tx1,
-                self.delimiter,
-                encoding,
-            )
-            .await;
-        });
-
+        encoding: Option<&'static Encoding>,
+    ) -> LocalBoxStream<'static, SourceRecord> {
         let (tx2, rx2) = mpsc::unbounded_channel();
-
-        tokio::spawn(async move {
-            write_to_output_stream(rx1, tx2, response_metadata, self.formatter).await;
-        });
+        let dead_letter = self
+            .dead_letter
+            .clone()
+            .map(|queue| (queue, self.request.url().to_string()));
+
+        match self.stream_format {
+            StreamFormat::Delimited => {
+                let (tx1, rx1) = mpsc::unbounded_channel();
+
+                tokio::spawn(async move {
+                    read_http_stream(
+                        response
+                            .bytes_stream()
+                            .map_err(reqwest::Error::without_url)
+                            .boxed(),
+                        tx1,
+                        self.delimiter,
+                        encoding,
+                    )
+                    .await;
+                });
+
+                tokio::spawn(async move {
+                    write_to_output_stream(
+                        rx1,
+                        tx2,
+                        response_metadata,
+                        self.formatter,
+                        self.record_key,
+                        dead_letter,
+                    )
+                    .await;
+                });
+            }
+            StreamFormat::Sse => {
+                let (tx1, rx1) = mpsc::unbounded_channel();
+                let parser = SseParser::new(self.sse_state);
+
+                tokio::spawn(async move {
+                    read_sse_stream(
+                        response
+                            .bytes_stream()
+                            .map_err(reqwest::Error::without_url)
+                            .boxed(),
+                        tx1,
+                        parser,
+                        self.sse_metadata,
+                    )
+                    .await;
+                });
+
+                tokio::spawn(async move {
+                    write_to_output_stream(
+                        rx1,
+                        tx2,
+                        response_metadata,
+                        self.formatter,
+                        self.record_key,
+                        dead_letter,
+                    )
+                    .await;
+                });
+            }
+        }
 
         Box::pin(UnboundedReceiverStream::new(rx2))
     }
@@ -104,9 +283,9 @@ impl HttpStreamingSource {
 
 async fn read_http_stream(
     mut stream: BoxStream<'_, Result<bytes::Bytes, reqwest::Error>>,
-    tx: mpsc::UnboundedSender<String>,
+    tx: mpsc::UnboundedSender<Bytes>,
     delimiter: Vec<u8>,
-    encoding: &'static Encoding,
+    encoding: Option<&'static Encoding>,
 ) {
     let mut buf = BytesMut::new();
 
@@ -124,11 +303,41 @@ async fn read_http_stream(
     }
 }
 
+async fn read_sse_stream(
+    mut stream: BoxStream<'_, Result<bytes::Bytes, reqwest::Error>>,
+    tx: mpsc::UnboundedSender<StreamRecord>,
+    mut parser: SseParser,
+    sse_metadata: bool,
+) {
+    while let Some(bytes) = stream.next().await {
+        match bytes {
+            Ok(bytes) => {
+                for event in parser.feed(bytes.as_ref()) {
+                    let record = StreamRecord {
+                        body: event.data.into(),
+                        event: sse_metadata.then_some(EventMetadata {
+                            name: event.event,
+                            id: event.id,
+                        }),
+                    };
+
+                    if let Err(e) = tx.send(record) {
+                        error!("Couldn't send event to formatting task: {e}");
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("could not read data from http response stream: {}", e);
+            }
+        }
+    }
+}
+
 fn dequeue_and_forward_records(
     buf: &mut BytesMut,
-    tx: &mpsc::UnboundedSender<String>,
+    tx: &mpsc::UnboundedSender<Bytes>,
     delimiter: &[u8],
-    encoding: &'static Encoding,
+    encoding: Option<&'static Encoding>,
 ) {
     while let Some(index) = first_delim_index(buf, delimiter) {
         let next_record = dequeue_next_record(buf, index, delimiter);
@@ -141,18 +350,27 @@ fn dequeue_and_forward_records(
     }
 }
 
-async fn write_to_output_stream(
-    mut rx: mpsc::UnboundedReceiver<String>,
-    tx: mpsc::UnboundedSender<String>,
+async fn write_to_output_stream<R: Into<StreamRecord>>(
+    mut rx: mpsc::UnboundedReceiver<R>,
+    tx: mpsc::UnboundedSender<SourceRecord>,
     response_metadata: HttpResponseMetadata,
     formatter: Arc<dyn Formatter + Sync + Send>,
+    record_key: Option<RecordKeyConfig>,
+    dead_letter: Option<(DeadLetterQueue, String)>,
 ) {
     while let Some(record) = rx.recv().await {
+        let record: StreamRecord = record.into();
+        let body = record.body.clone();
         let res = format_record(record, response_metadata.clone(), &formatter);
 
         match res {
             Ok(record) => {
-                let stream_result = tx.send(record);
+                let stream_result = tx.send(SourceRecord::new(
+                    record_key.as_ref(),
+                    &response_metadata,
+                    &body,
+                    record,
+                ));
 
                 if let Err(e) = stream_result {
                     error!("Couldn't send records to output stream: {e}");
@@ -160,19 +378,30 @@ async fn write_to_output_stream(
             }
             Err(err) => {
                 error!("Error formatting record: {err:?}");
+                if let Some((ref dead_letter, ref endpoint)) = dead_letter {
+                    let letter = DeadLetter::new(
+                        DeadLetterKind::FormatFailed,
+                        endpoint.clone(),
+                        &response_metadata,
+                        &body,
+                    )
+                    .with_error(&err);
+                    dead_letter.send(letter).await;
+                }
             }
         }
     }
 }
 
 fn format_record(
-    record: String,
+    record: StreamRecord,
     response_metadata: HttpResponseMetadata,
     formatter: &Arc<dyn Formatter + Sync + Send>,
-) -> Result<String> {
-    let formatter_input = HttpResponseRecord::new(response_metadata, record);
+) -> Result<Vec<u8>> {
+    let formatter_input =
+        HttpResponseRecord::new(response_metadata, record.body).with_event(record.event);
 
-    let formatted_record = formatter.to_string(&formatter_input).map_err(|err| {
+    let formatted_record = formatter.format(&formatter_input).map_err(|err| {
         anyhow!(
             "formatting failed, record: {:?}, reason: {:?}",
             formatter_input,
@@ -212,10 +441,14 @@ fn dequeue_next_record(buffer: &mut BytesMut, index: usize, delimiter: &[u8]) ->
     next_record
 }
 
-fn decoded_record_body(record_body: BytesMut, encoding: &'static Encoding) -> String {
-    let (text, _, _) = encoding.decode(&record_body);
-
-    text.into_owned()
+fn decoded_record_body(record_body: BytesMut, encoding: Option<&'static Encoding>) -> Bytes {
+    match encoding {
+        Some(encoding) => {
+            let (text, _, _) = encoding.decode(&record_body);
+            Bytes::from(text.into_owned())
+        }
+        None => record_body.freeze(),
+    }
 }
 
 // inspired by reqwest::Response::text()
@@ -254,7 +487,7 @@ mod test {
 
         let (tx, rx) = mpsc::unbounded_channel();
         tokio::spawn(async move {
-            read_http_stream(http_stream, tx, "!".into(), encoding_rs::UTF_8).await
+            read_http_stream(http_stream, tx, "!".into(), Some(encoding_rs::UTF_8)).await
         });
         let mut chunked_stream = Box::pin(UnboundedReceiverStream::new(rx));
 
@@ -276,7 +509,7 @@ mod test {
 
         let (tx, rx) = mpsc::unbounded_channel();
         tokio::spawn(async move {
-            read_http_stream(http_stream, tx, "!".into(), encoding_rs::UTF_8).await
+            read_http_stream(http_stream, tx, "!".into(), Some(encoding_rs::UTF_8)).await
         });
         let mut chunked_stream = Box::pin(UnboundedReceiverStream::new(rx));
 
@@ -296,7 +529,7 @@ mod test {
 
         let (tx, rx) = mpsc::unbounded_channel();
         tokio::spawn(async move {
-            read_http_stream(http_stream, tx, "!".into(), encoding_rs::UTF_8).await
+            read_http_stream(http_stream, tx, "!".into(), Some(encoding_rs::UTF_8)).await
         });
         let mut chunked_stream = Box::pin(UnboundedReceiverStream::new(rx));
 
@@ -317,7 +550,7 @@ mod test {
 
         let (tx, rx) = mpsc::unbounded_channel();
         tokio::spawn(async move {
-            read_http_stream(http_stream, tx, "!".into(), encoding_rs::UTF_8).await
+            read_http_stream(http_stream, tx, "!".into(), Some(encoding_rs::UTF_8)).await
         });
         let mut chunked_stream = Box::pin(UnboundedReceiverStream::new(rx));
 
@@ -339,7 +572,7 @@ mod test {
 
         let (tx, rx) = mpsc::unbounded_channel();
         tokio::spawn(async move {
-            read_http_stream(http_stream, tx, "!".into(), encoding_rs::UTF_8).await
+            read_http_stream(http_stream, tx, "!".into(), Some(encoding_rs::UTF_8)).await
         });
         let mut chunked_stream = Box::pin(UnboundedReceiverStream::new(rx));
 
@@ -350,6 +583,54 @@ mod test {
         assert_eq!(second_chunk.unwrap(), String::from(" Welcome to NY"));
     }
 
+    #[async_std::test]
+    async fn test_read_http_stream_keeps_raw_bytes() {
+        let inner_stream = futures::stream::iter(vec![
+            Ok(bytes::Bytes::from_static(&[0x08, 0x96, 0xff, b'!'])),
+            Ok(bytes::Bytes::from_static(&[0xfe, 0x00, b'!'])),
+        ]);
+        let http_stream = inner_stream.boxed();
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move { read_http_stream(http_stream, tx, "!".into(), None).await });
+        let mut chunked_stream = Box::pin(UnboundedReceiverStream::new(rx));
+
+        let first_chunk = chunked_stream.next().await;
+        assert_eq!(first_chunk.unwrap(), &[0x08, 0x96, 0xff][..]);
+
+        let second_chunk = chunked_stream.next().await;
+        assert_eq!(second_chunk.unwrap(), &[0xfe, 0x00][..]);
+    }
+
+    #[async_std::test]
+    async fn test_read_sse_stream_emits_event_data() {
+        let inner_stream = futures::stream::iter(vec![
+            Ok(bytes::Bytes::from("event:get request(s)\nid: 7\nda")),
+            Ok(bytes::Bytes::from("ta:{ \"gets\": 1 }\n\n")),
+            Ok(bytes::Bytes::from("data:{ \"gets\": 2 }\n\n")),
+        ]);
+        let http_stream = inner_stream.boxed();
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let parser = SseParser::new(SseState::default());
+        tokio::spawn(async move { read_sse_stream(http_stream, tx, parser, true).await });
+        let mut event_stream = Box::pin(UnboundedReceiverStream::new(rx));
+
+        let first = event_stream.next().await.unwrap();
+        assert_eq!(first.body, "{ \"gets\": 1 }");
+        assert_eq!(
+            first.event,
+            Some(EventMetadata {
+                name: "get request(s)".to_string(),
+                id: Some("7".to_string()),
+            })
+        );
+
+        let second = event_stream.next().await.unwrap();
+        assert_eq!(second.body, "{ \"gets\": 2 }");
+        assert_eq!(second.event.unwrap().name, "message");
+    }
+
     #[test]
     fn test_first_delim_index_finds_single_byte_delimiters() {
         assert_eq!(super::first_delim_index(b"", b"\n"), None);
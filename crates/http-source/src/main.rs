@@ -1,51 +1,141 @@
+mod auth;
 mod backoff;
+mod checkpoint;
+mod client;
 mod config;
+mod context;
+mod dead_letter;
 mod formatter;
 mod http_streaming_source;
+mod pagination;
+mod poll_state;
+mod proxy;
+mod record;
+mod record_selector;
+mod request_template;
+mod schedule;
 mod source;
+mod sse;
+mod status_policy;
+mod template;
+mod tls;
 mod websocket_source;
 
-use std::time::Duration;
-
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use async_std::stream::StreamExt;
-use backoff::Backoff;
-use config::HttpConfig;
+use auth::Authenticator;
+use backoff::{is_fatal, Backoff, GiveUpAction, RetriesExhausted, SetupFailed};
+use config::{BodyFormat, HttpConfig, OutputType, OversizedMessageAction};
+use context::SourceContext;
+use dead_letter::DeadLetterQueue;
 use fluvio::{RecordKey, TopicProducerPool};
 use fluvio_connector_common::{
     connector,
-    tracing::{debug, error, info, trace, warn},
+    tracing::{debug, error, info, info_span, trace, warn, Instrument},
     Source,
 };
-use futures::stream::LocalBoxStream;
-use url::Url;
+use futures::{future::try_join_all, stream::LocalBoxStream};
+use record::SourceRecord;
+use status_policy::StatusAction;
 
 use crate::http_streaming_source::HttpStreamingSource;
 use source::HttpSource;
 use websocket_source::WebSocketSource;
 
 const SIGNATURES: &str = concat!("InfinyOn HTTP Source Connector ", env!("CARGO_PKG_VERSION"));
-const BACKOFF_LIMIT: Duration = Duration::from_secs(1000);
 
-#[allow(unreachable_code)]
 #[connector(source)]
 async fn start(config: HttpConfig, producer: TopicProducerPool) -> Result<()> {
     debug!(?config);
 
-    let url = Url::parse(&config.endpoint.resolve()?)?;
-    let mut backoff = Backoff::new();
+    if let Some(ref record_key) = config.record_key {
+        record::validate_key_config(record_key)?;
+    }
+    if let Some(ref status_policy) = config.status_policy {
+        if status_policy.on_reject == StatusAction::DeadLetter && config.dead_letter_topic.is_none()
+        {
+            return Err(anyhow!(
+                "status_policy.on_reject: dead_letter requires dead_letter_topic"
+            ));
+        }
+    }
+    let endpoints = config.endpoint_configs()?;
+    if endpoints.iter().any(|(_, config)| {
+        config.body_format == BodyFormat::Raw && matches!(config.output_type, OutputType::Json)
+    }) {
+        return Err(anyhow!(
+            "body_format: raw requires output_type: text, use base64 for json output"
+        ));
+    }
+    if config.dead_letter_topic.is_none()
+        && endpoints.iter().any(|(_, config)| {
+            config.websocket_config.as_ref().is_some_and(|ws_config| {
+                ws_config.on_oversized_message == OversizedMessageAction::DeadLetter
+            })
+        })
+    {
+        return Err(anyhow!(
+            "websocket_config.on_oversized_message: dead_letter requires dead_letter_topic"
+        ));
+    }
+    let auth = match config.auth {
+        Some(ref auth) => Some(Authenticator::new(
+            auth,
+            &client::builder(&config)?.build()?,
+            config.timeout,
+        )?),
+        None => None,
+    };
+    let dead_letter = match config.dead_letter_topic {
+        Some(ref topic) => Some(DeadLetterQueue::new(topic).await?),
+        None => None,
+    };
+
+    let producer = &producer;
+    let runs = endpoints.into_iter().map(|(name, config)| {
+        let span = info_span!("endpoint", name = name.as_deref());
+        let auth = auth.clone();
+        let dead_letter = dead_letter.clone();
+        async move {
+            let context = SourceContext::new(&config, name, auth, dead_letter).await?;
+            run(&config, &context, producer).await
+        }
+        .instrument(span)
+    });
+    try_join_all(runs).await?;
+
+    Ok(())
+}
+
+/// Connects to a single endpoint and produces its records, reconnecting until a fatal error
+#[allow(unreachable_code)]
+async fn run(
+    config: &HttpConfig,
+    context: &SourceContext,
+    producer: &TopicProducerPool,
+) -> Result<()> {
+    let url = context.request.url(&context.request.vars())?;
+    let websocket = url.scheme() == "ws" || url.scheme() == "wss";
+    if config.checkpoint.is_some() && (websocket || config.stream) {
+        warn!("checkpoint only applies to polling mode and is ignored");
+    }
+    if config.schedule.is_some() && (websocket || config.stream) {
+        warn!("schedule only applies to polling mode and is ignored");
+    }
+    let mut backoff = Backoff::new(&config.retry.clone().unwrap_or_default());
 
     loop {
-        let stream = if url.scheme() == "ws" || url.scheme() == "wss" {
-            with_backoff(&config, &mut backoff, WebSocketSource::new).await
+        let stream = if websocket {
+            with_backoff(config, context, &mut backoff, WebSocketSource::new).await
         } else if config.stream {
-            with_backoff(&config, &mut backoff, HttpStreamingSource::new).await
+            with_backoff(config, context, &mut backoff, HttpStreamingSource::new).await
         } else {
-            with_backoff(&config, &mut backoff, HttpSource::new).await
+            with_backoff(config, context, &mut backoff, HttpSource::new).await
         };
 
         let mut stream = match stream {
             Ok(stream) => stream,
+            Err(err) if is_fatal(&err) => return Err(err),
             Err(_) => continue,
         };
 
@@ -53,11 +143,35 @@ async fn start(config: HttpConfig, producer: TopicProducerPool) -> Result<()> {
 
         while let Some(item) = stream.next().await {
             trace!(?item);
-            producer.send(RecordKey::NULL, item).await?;
+            let item = item?;
+            let key = match record::topic_key(
+                item.key,
+                context.endpoint_name.as_deref(),
+                config.output_type,
+            ) {
+                Some(key) => RecordKey::from(key),
+                None => RecordKey::NULL,
+            };
+            producer.send(key, item.value).await?;
+
+            if let (Some(checkpoint), Some(checkpoints)) = (item.checkpoint, &context.checkpoints) {
+                producer.flush().await?;
+                if let Err(err) = checkpoints.save(&checkpoint).await {
+                    error!("Unable to save checkpoint: {err:?}");
+                }
+            }
         }
 
         warn!("Disconnected from source endpoint, attempting reconnect...");
-        backoff = Backoff::new();
+        backoff.reset();
+
+        if let Some(retry) = context.sse_state.retry() {
+            debug!(
+                "Waiting {} before reconnecting as requested by the server",
+                humantime::format_duration(retry)
+            );
+            async_std::task::sleep(retry).await;
+        }
     }
 
     Ok(())
@@ -65,31 +179,42 @@ async fn start(config: HttpConfig, producer: TopicProducerPool) -> Result<()> {
 
 async fn with_backoff<'a, F, C>(
     config: &HttpConfig,
+    context: &SourceContext,
     backoff: &mut Backoff,
     new: F,
-) -> Result<LocalBoxStream<'a, String>>
+) -> Result<LocalBoxStream<'a, Result<SourceRecord>>>
 where
-    F: FnOnce(&HttpConfig) -> Result<C>,
-    C: Source<'a, String>,
+    F: FnOnce(&HttpConfig, &SourceContext) -> Result<C>,
+    C: Source<'a, Result<SourceRecord>>,
 {
-    let wait = backoff.next();
-
-    if wait > BACKOFF_LIMIT {
-        error!("Max retry reached, exiting");
-    }
-
-    match new(config)?.connect(None).await {
-        Ok(stream) => Ok(stream),
-        Err(err) => {
-            warn!(
-                "Error connecting to streaming source: \"{}\", reconnecting in {}.",
-                err,
-                humantime::format_duration(wait)
-            );
-
-            async_std::task::sleep(wait).await;
-
-            Err(err)
+    // invalid settings are not retried, only connection errors are
+    let source = new(config, context).context(SetupFailed)?;
+    let err = match source.connect(None).await {
+        Ok(stream) => return Ok(stream),
+        Err(err) if is_fatal(&err) => return Err(err),
+        Err(err) => err,
+    };
+
+    let wait = match backoff.next() {
+        Some(wait) => wait,
+        None => {
+            let attempts = backoff.failures();
+            if backoff.policy().on_give_up == GiveUpAction::Exit {
+                error!("Max retry reached, exiting");
+                return Err(err.context(RetriesExhausted { attempts }));
+            }
+
+            error!("Unable to connect after {attempts} attempts: \"{err}\", continuing to retry");
+            backoff.reset();
+            backoff.policy().initial_delay
         }
-    }
+    };
+    warn!(
+        "Error connecting to streaming source: \"{}\", reconnecting in {}.",
+        err,
+        humantime::format_duration(wait)
+    );
+    async_std::task::sleep(wait).await;
+
+    Err(err)
 }
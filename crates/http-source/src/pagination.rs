@@ -0,0 +1,491 @@
+use anyhow::{Context, Result};
+use fluvio_connector_common::tracing::warn;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    config::{PaginationConfig, PaginationEmit, PaginationStrategy},
+    formatter::HttpResponseMetadata,
+    record_selector::record_body,
+};
+
+/// Walks the pages of a single polling tick, starting from the configured endpoint.
+pub(crate) struct Paginator {
+    config: PaginationConfig,
+    base_url: Url,
+    current_url: Url,
+    state: PaginatorState,
+}
+
+/// Position of a paginator, saved in checkpoints to resume an interrupted tick.
+///
+/// URLs are not saved, they may hold credentials and are rebuilt from the current endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PaginatorState {
+    pages: usize,
+    page: u64,
+    offset: usize,
+    cursor: Option<String>,
+}
+
+impl Paginator {
+    pub(crate) fn new(config: PaginationConfig, base_url: &Url) -> Self {
+        let mut current_url = base_url.clone();
+        let page = match config.strategy {
+            PaginationStrategy::Page {
+                ref page_param,
+                start_page,
+                ..
+            } => {
+                set_query_param(&mut current_url, page_param, &start_page.to_string());
+                start_page
+            }
+            PaginationStrategy::Offset {
+                ref offset_param,
+                ref limit_param,
+                limit,
+            } => {
+                set_query_param(&mut current_url, offset_param, "0");
+                if let Some(limit_param) = limit_param {
+                    set_query_param(&mut current_url, limit_param, &limit.to_string());
+                }
+                0
+            }
+            _ => 0,
+        };
+
+        Self {
+            config,
+            base_url: base_url.clone(),
+            current_url,
+            state: PaginatorState {
+                pages: 0,
+                page,
+                offset: 0,
+                cursor: None,
+            },
+        }
+    }
+
+    /// Continues from a position saved with [`Paginator::state`], building the page URL from `base_url`.
+    pub(crate) fn resume(config: PaginationConfig, state: PaginatorState, base_url: &Url) -> Self {
+        let mut paginator = Self::new(config, base_url);
+        match paginator.config.strategy {
+            PaginationStrategy::Page { ref page_param, .. } => {
+                set_query_param(
+                    &mut paginator.current_url,
+                    page_param,
+                    &state.page.to_string(),
+                );
+            }
+            PaginationStrategy::Offset {
+                ref offset_param, ..
+            } => {
+                set_query_param(
+                    &mut paginator.current_url,
+                    offset_param,
+                    &state.offset.to_string(),
+                );
+            }
+            PaginationStrategy::Cursor {
+                ref cursor_param, ..
+            } => {
+                if let Some(ref cursor) = state.cursor {
+                    set_query_param(&mut paginator.current_url, cursor_param, cursor);
+                }
+            }
+            // links are not saved, see `state`
+            PaginationStrategy::LinkHeader | PaginationStrategy::NextUrl { .. } => {
+                return paginator
+            }
+        }
+        paginator.state = state;
+        paginator
+    }
+
+    /// Position to save in checkpoints, `None` when the next page is a link returned by the
+    /// server, which may echo credentials and is not saved.
+    pub(crate) fn state(&self) -> Option<&PaginatorState> {
+        match self.config.strategy {
+            PaginationStrategy::LinkHeader | PaginationStrategy::NextUrl { .. } => None,
+            _ => Some(&self.state),
+        }
+    }
+
+    /// URL of the page to be fetched next.
+    pub(crate) fn current_url(&self) -> &Url {
+        &self.current_url
+    }
+
+    /// Inspects the page just fetched and advances to the next one.
+    /// Returns `false` once pages are exhausted.
+    pub(crate) fn advance(&mut self, metadata: &HttpResponseMetadata, body: &str) -> Result<bool> {
+        self.state.pages += 1;
+        if let Some(max_pages) = self.config.max_pages {
+            if self.state.pages >= max_pages {
+                warn!(
+                    "Reached max_pages ({max_pages}), remaining pages are skipped until next tick"
+                );
+                return Ok(false);
+            }
+        }
+
+        let next_url = match self.config.strategy {
+            PaginationStrategy::LinkHeader => metadata
+                .header("link")
+                .and_then(next_link)
+                .map(|link| self.current_url.join(&link))
+                .transpose()
+                .context("invalid next page url in Link header")?,
+            PaginationStrategy::NextUrl {
+                ref next_url_pointer,
+            } => pointer_string(body, next_url_pointer)
+                .map(|link| self.current_url.join(&link))
+                .transpose()
+                .context("invalid next page url in response body")?,
+            PaginationStrategy::Cursor {
+                ref cursor_pointer,
+                ref cursor_param,
+            } => {
+                let cursor = pointer_string(body, cursor_pointer);
+                if cursor.is_some() && cursor == self.state.cursor {
+                    warn!("Pagination cursor did not change, stopping");
+                    return Ok(false);
+                }
+                self.state.cursor.clone_from(&cursor);
+                cursor.map(|cursor| {
+                    let mut url = self.base_url.clone();
+                    set_query_param(&mut url, cursor_param, &cursor);
+                    url
+                })
+            }
+            PaginationStrategy::Page {
+                ref page_param,
+                page_size,
+                ..
+            } => {
+                let count = self.items(body).map(|items| items.len()).unwrap_or(0);
+                if count == 0 || page_size.is_some_and(|size| count < size) {
+                    None
+                } else {
+                    self.state.page += 1;
+                    let mut url = self.current_url.clone();
+                    set_query_param(&mut url, page_param, &self.state.page.to_string());
+                    Some(url)
+                }
+            }
+            PaginationStrategy::Offset {
+                ref offset_param,
+                limit,
+                ..
+            } => {
+                let count = self.items(body).map(|items| items.len()).unwrap_or(0);
+                if count == 0 || count < limit {
+                    None
+                } else {
+                    self.state.offset += count;
+                    let mut url = self.current_url.clone();
+                    set_query_param(&mut url, offset_param, &self.state.offset.to_string());
+                    Some(url)
+                }
+            }
+        };
+
+        match next_url {
+            Some(url) => {
+                self.current_url = url;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Splits a page into record bodies according to the `emit` setting.
+    pub(crate) fn records(&self, body: String) -> Vec<String> {
+        match self.config.emit {
+            PaginationEmit::Page => vec![body],
+            PaginationEmit::Item => match self.items(&body) {
+                Some(items) => items.into_iter().map(record_body).collect(),
+                None => {
+                    warn!(
+                        "No items array found at \"{}\", page skipped",
+                        self.config.items_pointer
+                    );
+                    vec![]
+                }
+            },
+        }
+    }
+
+    fn items(&self, body: &str) -> Option<Vec<Value>> {
+        let mut json: Value = serde_json::from_str(body).ok()?;
+        match json.pointer_mut(&self.config.items_pointer)?.take() {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Extracts the `rel="next"` target of a `Link` header value.
+fn next_link(value: &str) -> Option<String> {
+    value.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        let is_next = params.split(';').any(|param| {
+            param.trim().strip_prefix("rel=").is_some_and(|rel| {
+                rel.trim_matches('"')
+                    .split_whitespace()
+                    .any(|rel| rel == "next")
+            })
+        });
+
+        is_next.then(|| {
+            target
+                .trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+    })
+}
+
+/// Reads a non-empty string or number found at `pointer` in a JSON body.
+fn pointer_string(body: &str, pointer: &str) -> Option<String> {
+    let json: Value = serde_json::from_str(body).ok()?;
+    match json.pointer(pointer)? {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Sets a query parameter, replacing any existing values
+pub(crate) fn set_query_param(url: &mut Url, key: &str, value: &str) {
+    let pairs = url
+        .query_pairs()
+        .filter(|(k, _)| k != key)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect::<Vec<_>>();
+
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(key, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::HttpHeader;
+
+    fn config(strategy: PaginationStrategy) -> PaginationConfig {
+        PaginationConfig {
+            strategy,
+            items_pointer: "/data".into(),
+            emit: PaginationEmit::Page,
+            max_pages: None,
+        }
+    }
+
+    fn base_url() -> Url {
+        Url::parse("http://127.0.0.1:8080/items?sort=asc").unwrap()
+    }
+
+    #[test]
+    fn test_next_link_parses_rel_next() {
+        let value = r#"<https://api.example/items?page=1>; rel="prev", <https://api.example/items?page=3>; rel="next""#;
+        assert_eq!(
+            next_link(value),
+            Some("https://api.example/items?page=3".to_string())
+        );
+        assert_eq!(next_link(r#"</items?page=1>; rel="first""#), None);
+        assert_eq!(
+            next_link("</items?page=2>; rel=next"),
+            Some("/items?page=2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_link_header_strategy_follows_relative_links() {
+        let mut paginator = Paginator::new(config(PaginationStrategy::LinkHeader), &base_url());
+        let metadata = HttpResponseMetadata {
+            headers: Some(vec![HttpHeader {
+                name: "link".into(),
+                value: r#"</items?page=2>; rel="next""#.into(),
+            }]),
+            ..Default::default()
+        };
+
+        assert!(paginator.advance(&metadata, "").unwrap());
+        assert_eq!(
+            paginator.current_url().as_str(),
+            "http://127.0.0.1:8080/items?page=2"
+        );
+        assert!(!paginator
+            .advance(&HttpResponseMetadata::default(), "")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_next_url_strategy_stops_on_null() {
+        let mut paginator = Paginator::new(
+            config(PaginationStrategy::NextUrl {
+                next_url_pointer: "/next".into(),
+            }),
+            &base_url(),
+        );
+        let metadata = HttpResponseMetadata::default();
+
+        assert!(paginator
+            .advance(&metadata, r#"{"next":"http://other/items?p=2"}"#)
+            .unwrap());
+        assert_eq!(paginator.current_url().as_str(), "http://other/items?p=2");
+        assert!(!paginator.advance(&metadata, r#"{"next":null}"#).unwrap());
+    }
+
+    #[test]
+    fn test_cursor_strategy_sets_query_param() {
+        let mut paginator = Paginator::new(
+            config(PaginationStrategy::Cursor {
+                cursor_pointer: "/meta/cursor".into(),
+                cursor_param: "cursor".into(),
+            }),
+            &base_url(),
+        );
+        let metadata = HttpResponseMetadata::default();
+        let body = r#"{"meta":{"cursor":"abc"}}"#;
+
+        assert!(paginator.advance(&metadata, body).unwrap());
+        assert_eq!(
+            paginator.current_url().as_str(),
+            "http://127.0.0.1:8080/items?sort=asc&cursor=abc"
+        );
+        assert!(!paginator.advance(&metadata, body).unwrap());
+    }
+
+    #[test]
+    fn test_page_strategy_stops_on_empty_page() {
+        let mut paginator = Paginator::new(
+            config(PaginationStrategy::Page {
+                page_param: "page".into(),
+                start_page: 1,
+                page_size: None,
+            }),
+            &base_url(),
+        );
+        let metadata = HttpResponseMetadata::default();
+        assert_eq!(
+            paginator.current_url().as_str(),
+            "http://127.0.0.1:8080/items?sort=asc&page=1"
+        );
+
+        assert!(paginator.advance(&metadata, r#"{"data":[1,2]}"#).unwrap());
+        assert_eq!(
+            paginator.current_url().as_str(),
+            "http://127.0.0.1:8080/items?sort=asc&page=2"
+        );
+        assert!(!paginator.advance(&metadata, r#"{"data":[]}"#).unwrap());
+    }
+
+    #[test]
+    fn test_resume_continues_from_saved_state() {
+        let strategy = PaginationStrategy::Page {
+            page_param: "page".into(),
+            start_page: 1,
+            page_size: None,
+        };
+        let metadata = HttpResponseMetadata::default();
+        let mut paginator = Paginator::new(config(strategy.clone()), &base_url());
+        assert!(paginator.advance(&metadata, r#"{"data":[1,2]}"#).unwrap());
+
+        let saved = serde_json::to_string(paginator.state().unwrap()).unwrap();
+        assert!(!saved.contains("127.0.0.1"));
+
+        // the URL is built from the current endpoint, e.g. after a key rotation
+        let base_url = Url::parse("http://127.0.0.1:8080/items?sort=asc&key=new").unwrap();
+        let mut resumed = Paginator::resume(
+            config(strategy),
+            serde_json::from_str(&saved).unwrap(),
+            &base_url,
+        );
+
+        assert_eq!(
+            resumed.current_url().as_str(),
+            "http://127.0.0.1:8080/items?sort=asc&key=new&page=2"
+        );
+        assert!(resumed.advance(&metadata, r#"{"data":[3]}"#).unwrap());
+        assert_eq!(
+            resumed.current_url().as_str(),
+            "http://127.0.0.1:8080/items?sort=asc&key=new&page=3"
+        );
+    }
+
+    #[test]
+    fn test_next_links_are_not_saved() {
+        let mut paginator = Paginator::new(
+            config(PaginationStrategy::NextUrl {
+                next_url_pointer: "/next".into(),
+            }),
+            &base_url(),
+        );
+
+        assert!(paginator
+            .advance(
+                &HttpResponseMetadata::default(),
+                r#"{"next":"http://other/items?p=2&api_key=s3cret"}"#
+            )
+            .unwrap());
+        assert!(paginator.state().is_none());
+    }
+
+    #[test]
+    fn test_offset_strategy_stops_on_short_page() {
+        let mut paginator = Paginator::new(
+            config(PaginationStrategy::Offset {
+                offset_param: "offset".into(),
+                limit_param: Some("limit".into()),
+                limit: 2,
+            }),
+            &base_url(),
+        );
+        let metadata = HttpResponseMetadata::default();
+
+        assert!(paginator.advance(&metadata, r#"{"data":[1,2]}"#).unwrap());
+        assert_eq!(
+            paginator.current_url().as_str(),
+            "http://127.0.0.1:8080/items?sort=asc&limit=2&offset=2"
+        );
+        assert!(!paginator.advance(&metadata, r#"{"data":[3]}"#).unwrap());
+    }
+
+    #[test]
+    fn test_max_pages_limits_pagination() {
+        let mut paginator = Paginator::new(
+            PaginationConfig {
+                max_pages: Some(1),
+                ..config(PaginationStrategy::LinkHeader)
+            },
+            &base_url(),
+        );
+
+        assert!(!paginator
+            .advance(&HttpResponseMetadata::default(), "")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_records_emits_items() {
+        let paginator = Paginator::new(
+            PaginationConfig {
+                emit: PaginationEmit::Item,
+                ..config(PaginationStrategy::LinkHeader)
+            },
+            &base_url(),
+        );
+
+        assert_eq!(
+            paginator.records(r#"{"data":[{"id":1},{"id":2}]}"#.into()),
+            vec![r#"{"id":1}"#.to_string(), r#"{"id":2}"#.to_string()]
+        );
+    }
+}
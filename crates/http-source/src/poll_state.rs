@@ -0,0 +1,209 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{
+    header::{HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Request,
+};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+
+use crate::{formatter::HttpResponseMetadata, pagination::PaginatorState};
+
+/// State carried between polling ticks.
+///
+/// Each value is stamped with the `now` of the tick it came from. With `max_in_flight` above one,
+/// ticks complete out of order and a value from an older tick never replaces a newer one.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PollState {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// `now` of the tick the validators came from
+    #[serde(default, with = "humantime_serde")]
+    validators_time: Option<SystemTime>,
+    /// SHA-256 of the body of each page of the previous tick, by page index
+    #[serde(default)]
+    page_hashes: Vec<PageHash>,
+    /// Position of a tick that was interrupted between pages
+    #[serde(default)]
+    pub pagination: Option<PaginationResume>,
+}
+
+/// Where an interrupted tick continues after a restart
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct PaginationResume {
+    /// `now` of the interrupted tick
+    #[serde(with = "humantime_serde")]
+    pub tick_time: SystemTime,
+    /// Index of the next page
+    #[serde(default)]
+    pub page: usize,
+    pub paginator: PaginatorState,
+}
+
+/// Body hash of one page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct PageHash {
+    hash: String,
+    /// `now` of the tick the page was fetched in
+    #[serde(with = "humantime_serde")]
+    tick_time: SystemTime,
+}
+
+impl Default for PageHash {
+    fn default() -> Self {
+        Self {
+            hash: String::new(),
+            tick_time: UNIX_EPOCH,
+        }
+    }
+}
+
+impl PollState {
+    /// Adds `If-None-Match` and `If-Modified-Since` headers from the previous response validators.
+    pub(crate) fn apply_conditional_headers(&self, request: &mut Request) {
+        let headers = request.headers_mut();
+
+        if let Some(value) = self.etag.as_deref().and_then(header_value) {
+            headers.insert(IF_NONE_MATCH, value);
+        }
+
+        if let Some(value) = self.last_modified.as_deref().and_then(header_value) {
+            headers.insert(IF_MODIFIED_SINCE, value);
+        }
+    }
+
+    /// Remembers the `ETag` and `Last-Modified` validators of a response fetched in the tick
+    /// started at `tick_time`, unless a newer tick already stored its own.
+    pub(crate) fn update_validators(
+        &mut self,
+        tick_time: SystemTime,
+        metadata: &HttpResponseMetadata,
+    ) {
+        if self.validators_time > Some(tick_time) {
+            return;
+        }
+
+        self.validators_time = Some(tick_time);
+        self.etag = metadata.header(ETAG.as_str()).map(str::to_string);
+        self.last_modified = metadata.header(LAST_MODIFIED.as_str()).map(str::to_string);
+    }
+
+    /// Records the body of the `page`-th page of a tick and returns `true` when it is identical to
+    /// the same page of the previous tick.
+    ///
+    /// Pages are compared by index rather than URL, since templates render a new URL on every tick.
+    /// The hash is only kept when no newer tick than `tick_time` already recorded that page.
+    pub(crate) fn is_duplicate(&mut self, tick_time: SystemTime, page: usize, body: &[u8]) -> bool {
+        let hash = STANDARD.encode(digest(&SHA256, body));
+        if self.page_hashes.len() <= page {
+            self.page_hashes.resize(page + 1, PageHash::default());
+        }
+
+        let previous = &mut self.page_hashes[page];
+        let duplicate = previous.hash == hash;
+        if previous.tick_time <= tick_time {
+            *previous = PageHash { hash, tick_time };
+        }
+        duplicate
+    }
+}
+
+fn header_value(value: &str) -> Option<HeaderValue> {
+    HeaderValue::from_str(value).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::HttpHeader;
+    use reqwest::{Method, Url};
+    use std::time::Duration;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn validators(etag: &str) -> HttpResponseMetadata {
+        HttpResponseMetadata {
+            headers: Some(vec![HttpHeader {
+                name: "etag".into(),
+                value: etag.into(),
+            }]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_conditional_headers_use_previous_validators() {
+        let mut state = PollState::default();
+        let mut request = Request::new(Method::GET, Url::parse("http://localhost/").unwrap());
+
+        state.apply_conditional_headers(&mut request);
+        assert!(request.headers().is_empty());
+
+        state.update_validators(
+            at(1),
+            &HttpResponseMetadata {
+                headers: Some(vec![
+                    HttpHeader {
+                        name: "etag".into(),
+                        value: "\"abc\"".into(),
+                    },
+                    HttpHeader {
+                        name: "last-modified".into(),
+                        value: "Wed, 21 Oct 2015 07:28:00 GMT".into(),
+                    },
+                ]),
+                ..Default::default()
+            },
+        );
+        state.apply_conditional_headers(&mut request);
+
+        assert_eq!(request.headers()[IF_NONE_MATCH], "\"abc\"");
+        assert_eq!(
+            request.headers()[IF_MODIFIED_SINCE],
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+    }
+
+    #[test]
+    fn test_is_duplicate_compares_bodies_per_page() {
+        let mut state = PollState::default();
+
+        assert!(!state.is_duplicate(at(1), 0, b"a"));
+        assert!(!state.is_duplicate(at(1), 1, b"a"));
+        assert!(state.is_duplicate(at(2), 0, b"a"));
+        assert!(!state.is_duplicate(at(3), 0, b"b"));
+        assert_eq!(state.page_hashes.len(), 2);
+    }
+
+    #[test]
+    fn test_older_ticks_do_not_replace_newer_state() {
+        let mut state = PollState::default();
+        let mut request = Request::new(Method::GET, Url::parse("http://localhost/").unwrap());
+
+        // the tick started at 2 completes before the one started at 1
+        state.update_validators(at(2), &validators("\"new\""));
+        state.update_validators(at(1), &validators("\"old\""));
+        state.apply_conditional_headers(&mut request);
+        assert_eq!(request.headers()[IF_NONE_MATCH], "\"new\"");
+
+        assert!(!state.is_duplicate(at(2), 0, b"new"));
+        assert!(!state.is_duplicate(at(1), 0, b"old"));
+        assert!(state.is_duplicate(at(3), 0, b"new"));
+    }
+
+    #[test]
+    fn test_page_hashes_are_stable() {
+        let mut state = PollState::default();
+        state.is_duplicate(at(1), 0, b"hello");
+
+        // persisted in checkpoints, so the hash must not change between builds
+        let json = serde_json::to_value(&state).unwrap();
+        assert_eq!(
+            json["page_hashes"][0]["hash"],
+            "LPJNul+wow4m6DsqxbninhsWHlwfp0JecwQzYpOLmCQ="
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Result};
+use fluvio_connector_common::tracing::warn;
+use serde_json::Value;
+
+use crate::{
+    checkpoint::Checkpoint,
+    config::{OutputType, RecordKeyConfig},
+    formatter::HttpResponseMetadata,
+    record_selector::record_body,
+};
+
+/// A record emitted by a source, ready to be sent to the topic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SourceRecord {
+    pub key: Option<String>,
+    pub value: Vec<u8>,
+    /// State to save once this record is produced
+    pub checkpoint: Option<Box<Checkpoint>>,
+}
+
+impl SourceRecord {
+    /// Builds a record, deriving its key from the response and the unformatted record body
+    pub(crate) fn new(
+        key_config: Option<&RecordKeyConfig>,
+        metadata: &HttpResponseMetadata,
+        body: &[u8],
+        value: Vec<u8>,
+    ) -> Self {
+        let key = key_config.and_then(|key_config| record_key(key_config, metadata, body));
+
+        Self {
+            key,
+            value,
+            checkpoint: None,
+        }
+    }
+}
+
+/// Key a record is produced with.
+///
+/// Records of named endpoints are keyed by the endpoint name unless `record_key` is set. Text
+/// records have no `endpoint` field, so their record key is then prefixed as `<endpoint>:<key>`.
+pub(crate) fn topic_key(
+    key: Option<String>,
+    endpoint_name: Option<&str>,
+    output_type: OutputType,
+) -> Option<String> {
+    match (key, endpoint_name) {
+        (Some(key), Some(name)) if matches!(output_type, OutputType::Text) => {
+            Some(format!("{name}:{key}"))
+        }
+        (Some(key), _) => Some(key),
+        (None, name) => name.map(str::to_string),
+    }
+}
+
+fn record_key(
+    key_config: &RecordKeyConfig,
+    metadata: &HttpResponseMetadata,
+    body: &[u8],
+) -> Option<String> {
+    match key_config {
+        RecordKeyConfig::Pointer(pointer) => body_pointer(body, pointer),
+        RecordKeyConfig::Header(name) => metadata.header(name).map(str::to_string),
+        RecordKeyConfig::Template(template) => {
+            let rendered = template.render(|expression| {
+                if let Some(name) = expression.strip_prefix("header:") {
+                    metadata
+                        .header(name.trim())
+                        .map(str::to_string)
+                        .ok_or_else(|| anyhow!("header \"{name}\" not found"))
+                } else if let Some(pointer) = expression.strip_prefix("body:") {
+                    body_pointer(body, pointer.trim())
+                        .ok_or_else(|| anyhow!("body pointer \"{pointer}\" not found"))
+                } else {
+                    Err(anyhow!("unknown record key expression \"{expression}\""))
+                }
+            });
+
+            match rendered {
+                Ok(key) => Some(key),
+                Err(err) => {
+                    warn!("Unable to render record key, producing record without key: {err}");
+                    None
+                }
+            }
+        }
+    }
+}
+
+fn body_pointer(body: &[u8], pointer: &str) -> Option<String> {
+    let mut json: Value = serde_json::from_slice(body).ok()?;
+    match json.pointer_mut(pointer)?.take() {
+        Value::Null => None,
+        value => Some(record_body(value)),
+    }
+}
+
+/// Checks that a key template only uses supported expressions
+pub(crate) fn validate_key_config(key_config: &RecordKeyConfig) -> Result<()> {
+    if let RecordKeyConfig::Template(template) = key_config {
+        template.render(|expression| {
+            if expression.starts_with("header:") || expression.starts_with("body:") {
+                Ok(String::new())
+            } else {
+                Err(anyhow!("unknown record key expression \"{expression}\""))
+            }
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{formatter::HttpHeader, template::Template};
+
+    fn metadata() -> HttpResponseMetadata {
+        HttpResponseMetadata {
+            headers: Some(vec![HttpHeader {
+                name: "x-tenant".into(),
+                value: "acme".into(),
+            }]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_key_from_body_pointer() {
+        let config = RecordKeyConfig::Pointer("/id".into());
+
+        let record = SourceRecord::new(Some(&config), &metadata(), br#"{"id":"a1"}"#, "v".into());
+        assert_eq!(record.key, Some("a1".to_string()));
+
+        let record = SourceRecord::new(Some(&config), &metadata(), br#"{"id":7}"#, "v".into());
+        assert_eq!(record.key, Some("7".to_string()));
+
+        let record = SourceRecord::new(Some(&config), &metadata(), b"not json", "v".into());
+        assert_eq!(record.key, None);
+    }
+
+    #[test]
+    fn test_key_from_header() {
+        let config = RecordKeyConfig::Header("X-Tenant".into());
+
+        let record = SourceRecord::new(Some(&config), &metadata(), b"", "v".into());
+
+        assert_eq!(record.key, Some("acme".to_string()));
+    }
+
+    #[test]
+    fn test_topic_key_tags_text_records_with_endpoint() {
+        let key = || Some("a1".to_string());
+
+        assert_eq!(topic_key(key(), None, OutputType::Text), key());
+        assert_eq!(
+            topic_key(key(), Some("orders"), OutputType::Text).as_deref(),
+            Some("orders:a1")
+        );
+        assert_eq!(topic_key(key(), Some("orders"), OutputType::Json), key());
+        assert_eq!(
+            topic_key(None, Some("orders"), OutputType::Json).as_deref(),
+            Some("orders")
+        );
+        assert_eq!(topic_key(None, None, OutputType::Text), None);
+    }
+
+    #[test]
+    fn test_key_from_template() {
+        let template = Template::parse("{{ header:x-tenant }}-{{ body:/id }}").unwrap();
+        let config = RecordKeyConfig::Template(template);
+
+        let record = SourceRecord::new(Some(&config), &metadata(), br#"{"id":1}"#, "v".into());
+        assert_eq!(record.key, Some("acme-1".to_string()));
+
+        let record = SourceRecord::new(Some(&config), &metadata(), br#"{}"#, "v".into());
+        assert_eq!(record.key, None);
+    }
+
+    #[test]
+    fn test_validate_key_config_rejects_unknown_expressions() {
+        let template = Template::parse("{{ query:id }}").unwrap();
+
+        assert!(validate_key_config(&RecordKeyConfig::Template(template)).is_err());
+    }
+}
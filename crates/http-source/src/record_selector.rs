@@ -0,0 +1,160 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Picks an array out of a JSON body so that each element becomes its own record.
+///
+/// Accepts either a JSON pointer (`/data/items`) or a simple JSONPath
+/// (`$.data.items[*]`, `$['data'][0].items`).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct RecordSelector {
+    pointer: String,
+}
+
+impl TryFrom<String> for RecordSelector {
+    type Error = anyhow::Error;
+
+    fn try_from(selector: String) -> Result<Self> {
+        let pointer = if selector.is_empty() || selector.starts_with('/') {
+            selector
+        } else if selector.starts_with('$') {
+            json_path_to_pointer(&selector)
+                .with_context(|| format!("invalid record_selector \"{selector}\""))?
+        } else {
+            return Err(anyhow!(
+                "record_selector \"{selector}\" must be a JSON pointer starting with '/' or a JSONPath starting with '$'"
+            ));
+        };
+
+        Ok(Self { pointer })
+    }
+}
+
+impl RecordSelector {
+    /// Returns the bodies of the records selected from `body`.
+    /// A selected array yields one record per element, any other value yields a single record.
+    pub(crate) fn select(&self, body: &str) -> Result<Vec<String>> {
+        Ok(self
+            .select_values(body)?
+            .into_iter()
+            .map(record_body)
+            .collect())
+    }
+
+    /// Same as [`RecordSelector::select`], keeping the selected JSON values
+    pub(crate) fn select_values(&self, body: &str) -> Result<Vec<Value>> {
+        let mut json: Value =
+            serde_json::from_str(body).context("record_selector requires a JSON body")?;
+        let selected = json
+            .pointer_mut(&self.pointer)
+            .ok_or_else(|| anyhow!("record_selector \"{}\" matched nothing", self.pointer))?
+            .take();
+
+        Ok(match selected {
+            Value::Array(values) => values,
+            value => vec![value],
+        })
+    }
+}
+
+/// Turns a JSON value into a record body. Strings are kept unquoted.
+pub(crate) fn record_body(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        value => value.to_string(),
+    }
+}
+
+fn json_path_to_pointer(path: &str) -> Result<String> {
+    let mut rest = path
+        .strip_prefix('$')
+        .ok_or_else(|| anyhow!("JSONPath must start with '$'"))?;
+    let mut pointer = String::new();
+
+    while !rest.is_empty() {
+        let segment = if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            let (name, tail) = tail.split_at(end);
+            rest = tail;
+            name.to_string()
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail
+                .find(']')
+                .ok_or_else(|| anyhow!("unclosed '[' in JSONPath"))?;
+            let (inner, tail) = tail.split_at(end);
+            rest = &tail[1..];
+            if inner == "*" {
+                if !rest.is_empty() {
+                    return Err(anyhow!("'[*]' is only supported at the end of JSONPath"));
+                }
+                break;
+            }
+            inner.trim_matches(|c| c == '\'' || c == '"').to_string()
+        } else {
+            return Err(anyhow!("unexpected character in JSONPath at \"{rest}\""));
+        };
+
+        if segment.is_empty() || segment == "*" {
+            return Err(anyhow!(
+                "wildcards and empty names are not supported in JSONPath"
+            ));
+        }
+
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    }
+
+    Ok(pointer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selector(s: &str) -> RecordSelector {
+        RecordSelector::try_from(s.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_json_path_to_pointer() {
+        assert_eq!(json_path_to_pointer("$").unwrap(), "");
+        assert_eq!(json_path_to_pointer("$.data").unwrap(), "/data");
+        assert_eq!(json_path_to_pointer("$.data[*]").unwrap(), "/data");
+        assert_eq!(
+            json_path_to_pointer("$['data'][0].items").unwrap(),
+            "/data/0/items"
+        );
+        assert_eq!(json_path_to_pointer("$['a/b']").unwrap(), "/a~1b");
+        assert!(json_path_to_pointer("$.data[*].id").is_err());
+        assert!(json_path_to_pointer("$..id").is_err());
+    }
+
+    #[test]
+    fn test_invalid_selector_is_rejected() {
+        assert!(RecordSelector::try_from("data".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_select_splits_arrays() {
+        let body = r#"{"data":[{"id":1},{"id":2},"three"]}"#;
+
+        let expected = vec![
+            r#"{"id":1}"#.to_string(),
+            r#"{"id":2}"#.to_string(),
+            "three".to_string(),
+        ];
+        assert_eq!(selector("/data").select(body).unwrap(), expected);
+        assert_eq!(selector("$.data[*]").select(body).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_select_single_value() {
+        assert_eq!(
+            selector("/data").select(r#"{"data":{"id":1}}"#).unwrap(),
+            vec![r#"{"id":1}"#.to_string()]
+        );
+        assert!(selector("/missing").select(r#"{"data":[]}"#).is_err());
+        assert!(selector("/data").select("not json").is_err());
+    }
+}
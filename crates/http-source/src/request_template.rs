@@ -0,0 +1,473 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use fluvio_connector_common::tracing::warn;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{Client, Method, Request, Url};
+use serde_json::Value;
+
+use crate::{
+    config::{FanOutConfig, HttpConfig},
+    record_selector::record_body,
+    template::Template,
+};
+
+/// Characters of `item` values escaped in the endpoint, everything but the unreserved ones
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Builds requests from the `endpoint`, `headers` and `body` templates.
+///
+/// Cloned instances share the tick counter and the time of the last successful request.
+#[derive(Clone)]
+pub(crate) struct RequestTemplate {
+    method: Method,
+    user_agent: String,
+    endpoint: Template,
+    headers: Vec<(HeaderName, Template)>,
+    body: Option<Template>,
+    state: Arc<Mutex<TemplateState>>,
+}
+
+#[derive(Debug)]
+struct TemplateState {
+    tick_count: u64,
+    last_success_time: SystemTime,
+}
+
+/// Values of the template variables for a single request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TemplateVars {
+    pub now: SystemTime,
+    pub last_success_time: SystemTime,
+    pub tick_count: u64,
+    /// Random hex string generated for every tick or connection attempt
+    pub nonce: String,
+    /// Parent item of a `fan_out` child request
+    pub item: Option<Value>,
+}
+
+impl RequestTemplate {
+    pub(crate) fn new(config: &HttpConfig) -> Result<Self> {
+        let endpoint = config
+            .endpoint
+            .as_ref()
+            .ok_or_else(|| anyhow!("endpoint is required"))?
+            .resolve()?;
+        let endpoint = Template::parse(&endpoint).context("parse endpoint")?;
+        let headers = config
+            .headers
+            .iter()
+            .enumerate()
+            .map(|(index, header)| parse_header(index, &header.resolve()?))
+            .filter_map(Result::transpose)
+            .collect::<Result<Vec<_>>>()?;
+        let body = config
+            .body
+            .as_deref()
+            .map(Template::parse)
+            .transpose()
+            .context("parse body")?;
+
+        let template = Self {
+            method: config.method.parse()?,
+            user_agent: config.user_agent.clone(),
+            endpoint,
+            headers,
+            body,
+            state: Arc::new(Mutex::new(TemplateState {
+                tick_count: 0,
+                last_success_time: SystemTime::now(),
+            })),
+        };
+
+        // catch unknown variables and invalid URLs before the first tick
+        template.url(&template.vars())?;
+        template.headers(&template.vars())?;
+        template.body(&template.vars())?;
+
+        Ok(template)
+    }
+
+    /// Template of the `fan_out` child requests, sharing the tick state of this template
+    pub(crate) fn fan_out(&self, config: &FanOutConfig) -> Result<Self> {
+        let endpoint = Template::parse(&config.endpoint).context("parse fan_out.endpoint")?;
+        let child_headers = config
+            .headers
+            .iter()
+            .enumerate()
+            .map(|(index, header)| parse_header(index, &header.resolve()?))
+            .filter_map(Result::transpose)
+            .collect::<Result<Vec<_>>>()
+            .context("parse fan_out.headers")?;
+        let body = config
+            .body
+            .as_deref()
+            .map(Template::parse)
+            .transpose()
+            .context("parse fan_out.body")?;
+
+        let template = Self {
+            method: config.method.parse()?,
+            user_agent: self.user_agent.clone(),
+            endpoint,
+            headers: self.headers.iter().cloned().chain(child_headers).collect(),
+            body,
+            state: self.state.clone(),
+        };
+
+        // item values are only known per request, so they render as placeholders here
+        let vars = self.vars();
+        let resolve = |expression: &str| match is_item(expression) {
+            true => Ok("item".to_string()),
+            false => vars.resolve(expression),
+        };
+        Url::parse(&template.endpoint.render(resolve)?)
+            .context("unable to parse fan_out.endpoint")?;
+        for (_, value) in template.headers.iter() {
+            value.render(resolve)?;
+        }
+        if let Some(ref body) = template.body {
+            body.render(resolve)?;
+        }
+
+        Ok(template)
+    }
+
+    /// Starts a new tick, returning the variables used to render its requests
+    pub(crate) fn tick(&self) -> TemplateVars {
+        let mut state = self.lock();
+        state.tick_count += 1;
+
+        TemplateVars {
+            now: SystemTime::now(),
+            last_success_time: state.last_success_time,
+            tick_count: state.tick_count,
+            nonce: nonce(),
+            item: None,
+        }
+    }
+
+    /// Restores the time of the last successful tick from a checkpoint
+    pub(crate) fn restore(&self, last_success_time: SystemTime) {
+        self.lock().last_success_time = last_success_time;
+    }
+
+    /// Remembers the time of a tick whose requests all succeeded.
+    ///
+    /// Concurrent ticks can complete out of order, so an older tick never moves the time back.
+    pub(crate) fn succeeded(&self, vars: &TemplateVars) {
+        let mut state = self.lock();
+        state.last_success_time = state.last_success_time.max(vars.now);
+    }
+
+    /// Time of the latest tick whose requests all succeeded
+    pub(crate) fn last_success_time(&self) -> SystemTime {
+        self.lock().last_success_time
+    }
+
+    /// Builds the request sent with reqwest
+    pub(crate) fn build(&self, client: &Client, vars: &TemplateVars) -> Result<Request> {
+        let mut request = client
+            .request(self.method.clone(), self.url(vars)?)
+            .header(reqwest::header::USER_AGENT, self.user_agent.clone())
+            .headers(self.headers(vars)?);
+
+        if let Some(body) = self.body(vars)? {
+            request = request.body(body);
+        }
+
+        request.build().context("unable to build http request")
+    }
+
+    pub(crate) fn url(&self, vars: &TemplateVars) -> Result<Url> {
+        // item values are data, so they cannot add path segments or query parameters
+        let endpoint = self.endpoint.render(|expression| {
+            let value = vars.resolve(expression)?;
+            Ok(match is_item(expression) {
+                true => utf8_percent_encode(&value, URL_COMPONENT).to_string(),
+                false => value,
+            })
+        })?;
+
+        Url::parse(&endpoint).context("unable to parse http endpoint")
+    }
+
+    pub(crate) fn headers(&self, vars: &TemplateVars) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in self.headers.iter() {
+            let value = value.render(|expression| vars.resolve(expression))?;
+            headers.append(
+                name.clone(),
+                HeaderValue::from_str(&value)
+                    .with_context(|| format!("header {name} has an invalid value"))?,
+            );
+        }
+
+        Ok(headers)
+    }
+
+    fn body(&self, vars: &TemplateVars) -> Result<Option<String>> {
+        self.body
+            .as_ref()
+            .map(|body| body.render(|expression| vars.resolve(expression)))
+            .transpose()
+    }
+
+    /// Variables of the current tick without starting a new one
+    pub(crate) fn vars(&self) -> TemplateVars {
+        let state = self.lock();
+
+        TemplateVars {
+            now: SystemTime::now(),
+            last_success_time: state.last_success_time,
+            tick_count: state.tick_count,
+            nonce: nonce(),
+            item: None,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TemplateState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl TemplateVars {
+    pub(crate) fn render(&self, template: &Template) -> Result<String> {
+        template.render(|expression| self.resolve(expression))
+    }
+
+    /// Resolves `variable` or `variable | helper` expressions
+    fn resolve(&self, expression: &str) -> Result<String> {
+        let (name, helper) = match expression.split_once('|') {
+            Some((name, helper)) => (name.trim(), Some(helper.trim())),
+            None => (expression, None),
+        };
+
+        if is_item(name) {
+            no_helper(name, helper)?;
+            let item = self
+                .item
+                .as_ref()
+                .ok_or_else(|| anyhow!("\"{name}\" is only available in fan_out requests"))?;
+            let value = match name.strip_prefix("item:") {
+                Some(pointer) => item
+                    .pointer(pointer.trim())
+                    .ok_or_else(|| anyhow!("parent item has no value at \"{}\"", pointer.trim()))?,
+                None => item,
+            };
+            return Ok(record_body(value.clone()));
+        }
+
+        if let Some(variable) = name.strip_prefix("env:") {
+            no_helper(name, helper)?;
+            return std::env::var(variable.trim())
+                .with_context(|| format!("environment variable \"{}\" not set", variable.trim()));
+        }
+
+        match name {
+            "now" => format_time(self.now, helper),
+            "last_success_time" => format_time(self.last_success_time, helper),
+            "tick_count" => {
+                no_helper(name, helper)?;
+                Ok(self.tick_count.to_string())
+            }
+            "nonce" => {
+                no_helper(name, helper)?;
+                Ok(self.nonce.clone())
+            }
+            _ => Err(anyhow!("unknown template variable \"{name}\"")),
+        }
+    }
+}
+
+fn nonce() -> String {
+    format!("{:032x}", fastrand::u128(..))
+}
+
+/// `item` or `item:<pointer>` expressions of fan_out requests
+fn is_item(expression: &str) -> bool {
+    let name = expression.split('|').next().unwrap_or_default().trim();
+    name == "item" || name.starts_with("item:")
+}
+
+fn format_time(time: SystemTime, helper: Option<&str>) -> Result<String> {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    match helper.unwrap_or("rfc3339") {
+        "rfc3339" => Ok(humantime::format_rfc3339_seconds(time).to_string()),
+        "rfc3339_millis" => Ok(humantime::format_rfc3339_millis(time).to_string()),
+        "epoch" => Ok(since_epoch.as_secs().to_string()),
+        "epoch_millis" => Ok(since_epoch.as_millis().to_string()),
+        helper => Err(anyhow!("unknown template helper \"{helper}\"")),
+    }
+}
+
+fn no_helper(name: &str, helper: Option<&str>) -> Result<()> {
+    match helper {
+        Some(helper) => Err(anyhow!("\"{name}\" does not support helper \"{helper}\"")),
+        None => Ok(()),
+    }
+}
+
+/// Parses a `name: value` entry of the `headers` config
+/// Parses a `name: value` header, skipping entries without a `:` as earlier versions did
+fn parse_header(index: usize, header: &str) -> Result<Option<(HeaderName, Template)>> {
+    let Some((name, value)) = header.split_once(':') else {
+        warn!("headers[{index}] is not in \"name: value\" format and is ignored");
+        return Ok(None);
+    };
+
+    Ok(Some((
+        HeaderName::from_bytes(name.trim().as_bytes())
+            .with_context(|| format!("headers[{index}] has an invalid name"))?,
+        Template::parse(value.trim()).with_context(|| format!("parse headers[{index}]"))?,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn vars() -> TemplateVars {
+        TemplateVars {
+            now: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            last_success_time: UNIX_EPOCH + Duration::from_secs(1_699_999_940),
+            tick_count: 3,
+            nonce: "0123456789abcdef0123456789abcdef".to_string(),
+            item: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_variables_and_helpers() {
+        let vars = vars();
+
+        assert_eq!(vars.resolve("now").unwrap(), "2023-11-14T22:13:20Z");
+        assert_eq!(
+            vars.resolve("now | rfc3339_millis").unwrap(),
+            "2023-11-14T22:13:20.123Z"
+        );
+        assert_eq!(vars.resolve("now|epoch").unwrap(), "1700000000");
+        assert_eq!(vars.resolve("now | epoch_millis").unwrap(), "1700000000123");
+        assert_eq!(
+            vars.resolve("last_success_time | epoch").unwrap(),
+            "1699999940"
+        );
+        assert_eq!(vars.resolve("tick_count").unwrap(), "3");
+        assert_eq!(
+            vars.resolve("nonce").unwrap(),
+            "0123456789abcdef0123456789abcdef"
+        );
+        assert_eq!(
+            vars.resolve("env:PATH").unwrap(),
+            std::env::var("PATH").unwrap()
+        );
+
+        assert!(vars.resolve("yesterday").is_err());
+        assert!(vars.resolve("now | iso").is_err());
+        assert!(vars.resolve("tick_count | epoch").is_err());
+        assert!(vars.resolve("item").is_err());
+    }
+
+    #[test]
+    fn test_resolve_fan_out_item() {
+        let vars = TemplateVars {
+            item: Some(serde_json::json!({"id": 42, "name": "acme", "tags": ["a"]})),
+            ..vars()
+        };
+
+        assert_eq!(vars.resolve("item:/id").unwrap(), "42");
+        assert_eq!(vars.resolve("item: /name").unwrap(), "acme");
+        assert_eq!(vars.resolve("item:/tags").unwrap(), r#"["a"]"#);
+        assert_eq!(
+            vars.resolve("item").unwrap(),
+            r#"{"id":42,"name":"acme","tags":["a"]}"#
+        );
+        assert!(vars.resolve("item:/missing").is_err());
+        assert!(vars.resolve("item:/id | epoch").is_err());
+    }
+
+    #[test]
+    fn test_fan_out_endpoint_escapes_item_values() {
+        let config: HttpConfig = serde_json::from_str(
+            r#"{"endpoint": "http://localhost/accounts", "fan_out": {"endpoint": "http://localhost/accounts/{{ item:/id }}/events?name={{ item:/name }}"}}"#,
+        )
+        .unwrap();
+        let parent = RequestTemplate::new(&config).unwrap();
+        let child = parent.fan_out(config.fan_out.as_ref().unwrap()).unwrap();
+        let vars = TemplateVars {
+            item: Some(serde_json::json!({"id": "a/b?c#d", "name": "x&y=z w"})),
+            ..vars()
+        };
+
+        let url = child.url(&vars).unwrap();
+        assert_eq!(
+            url.as_str(),
+            "http://localhost/accounts/a%2Fb%3Fc%23d/events?name=x%26y%3Dz%20w"
+        );
+        assert_eq!(url.path_segments().unwrap().count(), 3);
+        assert_eq!(
+            url.query_pairs().collect::<Vec<_>>(),
+            vec![("name".into(), "x&y=z w".into())]
+        );
+    }
+
+    #[test]
+    fn test_last_success_time_only_moves_forward() {
+        let config: HttpConfig =
+            serde_json::from_str(r#"{"endpoint": "http://localhost/"}"#).unwrap();
+        let template = RequestTemplate::new(&config).unwrap();
+        let older = vars();
+        let newer = TemplateVars {
+            now: older.now + Duration::from_secs(1),
+            ..vars()
+        };
+        template.restore(older.last_success_time);
+
+        // the newer tick completes first
+        template.succeeded(&newer);
+        template.succeeded(&older);
+        assert_eq!(template.last_success_time(), newer.now);
+        assert_eq!(template.tick().last_success_time, newer.now);
+    }
+
+    #[test]
+    fn test_render_endpoint_and_headers() {
+        let template = Template::parse(
+            "http://localhost/events?since={{ last_success_time | epoch }}&until={{ now | epoch }}",
+        )
+        .unwrap();
+        let vars = vars();
+
+        let url = template
+            .render(|expression| vars.resolve(expression))
+            .unwrap();
+        assert_eq!(
+            url,
+            "http://localhost/events?since=1699999940&until=1700000000"
+        );
+
+        let (name, value) = parse_header(0, "X-Tick: {{ tick_count }}")
+            .unwrap()
+            .unwrap();
+        assert_eq!(name, "x-tick");
+        assert_eq!(
+            value.render(|expression| vars.resolve(expression)).unwrap(),
+            "3"
+        );
+        assert!(parse_header(1, "missing separator").unwrap().is_none());
+        assert!(parse_header(2, "bad name: value").is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+use fluvio_connector_common::tracing::error;
+use futures::{stream::LocalBoxStream, StreamExt};
+use serde::Deserialize;
+use tokio::time::Interval;
+use tokio_stream::wrappers::IntervalStream;
+
+/// Cron expression evaluated in a timezone, e.g. `0 6 * * MON-FRI` in `UTC`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "ScheduleConfig")]
+pub(crate) struct Schedule {
+    cron: Cron,
+    timezone: Tz,
+}
+
+#[derive(Deserialize)]
+struct ScheduleConfig {
+    cron: String,
+    #[serde(default = "Default::default")]
+    timezone: Option<String>,
+}
+
+impl TryFrom<ScheduleConfig> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(config: ScheduleConfig) -> Result<Self> {
+        let cron = config
+            .cron
+            .parse()
+            .with_context(|| format!("invalid schedule.cron \"{}\"", config.cron))?;
+        let timezone = match config.timezone {
+            Some(timezone) => timezone
+                .parse()
+                .map_err(|_| anyhow!("unknown schedule.timezone \"{timezone}\""))?,
+            None => Tz::UTC,
+        };
+        let schedule = Self { cron, timezone };
+
+        // patterns like "0 0 30 2 *" parse but never match
+        schedule.next_after(Utc::now())?;
+
+        Ok(schedule)
+    }
+}
+
+impl Schedule {
+    /// First time after `time` matching the cron expression
+    fn next_after(&self, time: DateTime<Utc>) -> Result<DateTime<Utc>> {
+        let local = time.with_timezone(&self.timezone);
+        let next = self
+            .cron
+            .find_next_occurrence(&local, false)
+            .with_context(|| format!("schedule \"{}\" has no next occurrence", self.cron))?;
+
+        Ok(next.with_timezone(&Utc))
+    }
+}
+
+/// Source of the polling ticks: a fixed `interval` or a cron `schedule`
+pub(crate) enum Ticker {
+    Interval(Interval),
+    Schedule(Box<Schedule>),
+}
+
+impl Ticker {
+    pub(crate) fn into_stream(self) -> LocalBoxStream<'static, ()> {
+        match self {
+            Self::Interval(interval) => IntervalStream::new(interval).map(|_| ()).boxed_local(),
+            Self::Schedule(schedule) => {
+                futures::stream::unfold((schedule, None), |(schedule, last)| async move {
+                    // never fire twice for the same occurrence if the sleep ended early
+                    let now = match last {
+                        Some(last) if last > Utc::now() => last,
+                        _ => Utc::now(),
+                    };
+                    let next = match schedule.next_after(now) {
+                        Ok(next) => next,
+                        Err(err) => {
+                            error!("{err:?}");
+                            return None;
+                        }
+                    };
+
+                    let wait = (next - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+                    async_std::task::sleep(wait).await;
+
+                    Some(((), (schedule, Some(next))))
+                })
+                .boxed_local()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(json: &str) -> Result<Schedule> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let weekdays = schedule(r#"{"cron": "0 6 * * MON-FRI"}"#).unwrap();
+        // Friday 2024-10-18 07:00 UTC, the next weekday is Monday
+        assert_eq!(
+            weekdays.next_after(utc("2024-10-18T07:00:00Z")).unwrap(),
+            utc("2024-10-21T06:00:00Z")
+        );
+
+        let hourly = schedule(r#"{"cron": "5 * * * *"}"#).unwrap();
+        assert_eq!(
+            hourly.next_after(utc("2024-10-18T07:05:00Z")).unwrap(),
+            utc("2024-10-18T08:05:00Z")
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_in_timezone() {
+        let berlin = schedule(r#"{"cron": "0 6 * * *", "timezone": "Europe/Berlin"}"#).unwrap();
+
+        // CEST is UTC+2 in summer and CET is UTC+1 in winter
+        assert_eq!(
+            berlin.next_after(utc("2024-07-01T00:00:00Z")).unwrap(),
+            utc("2024-07-01T04:00:00Z")
+        );
+        assert_eq!(
+            berlin.next_after(utc("2024-12-01T00:00:00Z")).unwrap(),
+            utc("2024-12-01T05:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_invalid_schedule() {
+        assert!(schedule(r#"{"cron": "every day"}"#).is_err());
+        assert!(schedule(r#"{"cron": "0 6 * * *", "timezone": "Mars/Olympus"}"#).is_err());
+        assert!(schedule(r#"{"cron": "0 0 30 2 *"}"#).is_err());
+    }
+}
@@ -1,100 +1,879 @@
-use std::sync::Arc;
+use std::{
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
 
 use crate::{
-    config::HttpConfig,
+    auth::Authenticator,
+    backoff::{is_fatal, Backoff, GiveUpAction, RetriesExhausted, RetryPolicy},
+    checkpoint::Checkpoint,
+    client,
+    config::{BodyFormat, HttpConfig, MissedTick, PaginationConfig, RecordKeyConfig},
+    context::SourceContext,
+    dead_letter::{DeadLetter, DeadLetterKind, DeadLetterQueue},
     formatter::{formatter, Formatter, HttpResponseMetadata, HttpResponseRecord},
+    pagination::Paginator,
+    poll_state::{PaginationResume, PollState},
+    record::SourceRecord,
+    record_selector::RecordSelector,
+    request_template::{RequestTemplate, TemplateVars},
+    schedule::Ticker,
+    status_policy::{retry_after, RejectedStatus, StatusAction, StatusPolicy},
 };
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result};
 use async_trait::async_trait;
+use bytes::Bytes;
 use fluvio::Offset;
 use fluvio_connector_common::{
-    tracing::{error, trace},
+    tracing::{debug, error, trace, warn},
     Source,
 };
 use futures::{stream::LocalBoxStream, StreamExt};
-use reqwest::{Client, RequestBuilder, Url};
-use tokio::time::Interval;
-use tokio_stream::wrappers::IntervalStream;
+use reqwest::{Client, Request, Response, StatusCode};
+use serde_json::Value;
+use tokio::time::MissedTickBehavior;
 
 pub(crate) struct HttpSource {
-    interval: Interval,
-    request: RequestBuilder,
+    ticker: Ticker,
+    max_in_flight: usize,
+    client: Client,
+    request: RequestTemplate,
     formatter: Arc<dyn Formatter + Sync + Send>,
+    pagination: Option<PaginationConfig>,
+    record_selector: Option<RecordSelector>,
+    record_key: Option<RecordKeyConfig>,
+    fan_out: Option<FanOut>,
+    conditional_requests: bool,
+    dedup: bool,
+    body_format: BodyFormat,
+    state: Arc<Mutex<PollState>>,
+    auth: Option<Authenticator>,
+    status_policy: Option<StatusPolicy>,
+    retry: Option<RetryPolicy>,
+    dead_letter: Option<DeadLetterQueue>,
+    checkpoint: bool,
 }
 
 impl HttpSource {
-    pub(crate) fn new(config: &HttpConfig) -> Result<Self> {
-        let client = Client::new();
-        let method = config.method.parse()?;
-        let url =
-            Url::parse(&config.endpoint.resolve()?).context("unable to parse http endpoint")?;
-        let mut request = client.request(method, url);
-
-        request = request.header(reqwest::header::USER_AGENT, config.user_agent.clone());
-        let headers = config
-            .headers
-            .iter()
-            .map(|h| h.resolve().unwrap_or_default())
-            .collect::<Vec<_>>();
-
-        for (key, value) in headers.iter().flat_map(|h| h.split_once(':')) {
-            request = request.header(key, value);
-        }
+    pub(crate) fn new(config: &HttpConfig, context: &SourceContext) -> Result<Self> {
+        let ticker = match config.schedule {
+            Some(ref schedule) => Ticker::Schedule(Box::new(schedule.clone())),
+            None => {
+                let mut interval = tokio::time::interval(config.interval);
+                interval.set_missed_tick_behavior(match config.missed_tick {
+                    MissedTick::Burst => MissedTickBehavior::Burst,
+                    MissedTick::Delay => MissedTickBehavior::Delay,
+                    MissedTick::Skip => MissedTickBehavior::Skip,
+                });
+                Ticker::Interval(interval)
+            }
+        };
 
-        if let Some(ref body) = config.body {
-            request = request.body(body.clone());
+        let mut client = client::builder(config)?;
+        if let Some(timeout) = config.timeout {
+            client = client.timeout(timeout);
         }
 
-        let interval = tokio::time::interval(config.interval);
-
-        let formatter = formatter(config.output_type, config.output_parts);
+        let formatter = formatter(
+            config.output_type,
+            config.output_parts,
+            config.body_format,
+            context.endpoint_name.clone(),
+        );
+        let fan_out = config
+            .fan_out
+            .as_ref()
+            .zip(context.fan_out_request.clone())
+            .map(|(fan_out, template)| FanOut {
+                items: fan_out.items.clone(),
+                template,
+                max_concurrency: fan_out.max_concurrency.max(1),
+            });
 
         Ok(Self {
-            interval,
-            request,
+            ticker,
+            max_in_flight: config.max_in_flight.max(1),
+            client: client.build().context("unable to build http client")?,
+            request: context.request.clone(),
             formatter,
+            pagination: config.pagination.clone(),
+            record_selector: config.record_selector.clone(),
+            record_key: config.record_key.clone(),
+            fan_out,
+            conditional_requests: config.conditional_requests,
+            dedup: config.dedup,
+            body_format: config.body_format,
+            state: context.poll_state.clone(),
+            auth: context.auth.clone(),
+            status_policy: config.status_policy.clone(),
+            retry: config.retry.clone(),
+            dead_letter: context.dead_letter.clone(),
+            checkpoint: context.checkpoints.is_some(),
         })
     }
 }
 
 #[async_trait]
-impl<'a> Source<'a, String> for HttpSource {
-    async fn connect(self, _offset: Option<Offset>) -> Result<LocalBoxStream<'a, String>> {
-        let stream = IntervalStream::new(self.interval).filter_map(move |_| {
-            let builder = self.request.try_clone();
-            let formatter = self.formatter.clone();
-
-            async move {
-                match request(builder, formatter.as_ref()).await {
-                    Ok(res) => {
-                        trace!("Request execution completed: {res}");
-
-                        Some(res)
+impl<'a> Source<'a, Result<SourceRecord>> for HttpSource {
+    async fn connect(
+        self,
+        _offset: Option<Offset>,
+    ) -> Result<LocalBoxStream<'a, Result<SourceRecord>>> {
+        let max_in_flight = self.max_in_flight;
+        // the ticker is not polled while max_in_flight ticks are running,
+        // so ticks missed in the meantime follow the missed_tick policy
+        let stream = self.ticker.into_stream().map(move |_| {
+            poll(
+                self.client.clone(),
+                self.request.clone(),
+                PollOptions {
+                    pagination: self.pagination.clone(),
+                    record_selector: self.record_selector.clone(),
+                    record_key: self.record_key.clone(),
+                    fan_out: self.fan_out.clone(),
+                    conditional_requests: self.conditional_requests,
+                    dedup: self.dedup,
+                    body_format: self.body_format,
+                    status_policy: self.status_policy.clone(),
+                    retry: self.retry.clone(),
+                    checkpoint: self.checkpoint,
+                },
+                self.state.clone(),
+                self.auth.clone(),
+                self.dead_letter.clone(),
+                self.formatter.clone(),
+            )
+        });
+
+        Ok(stream.flatten_unordered(max_in_flight).boxed_local())
+    }
+}
+
+/// Per-tick settings of the polling loop
+struct PollOptions {
+    pagination: Option<PaginationConfig>,
+    record_selector: Option<RecordSelector>,
+    record_key: Option<RecordKeyConfig>,
+    fan_out: Option<FanOut>,
+    conditional_requests: bool,
+    dedup: bool,
+    body_format: BodyFormat,
+    status_policy: Option<StatusPolicy>,
+    retry: Option<RetryPolicy>,
+    checkpoint: bool,
+}
+
+/// Child requests sent for each item of a parent response
+#[derive(Clone)]
+struct FanOut {
+    items: RecordSelector,
+    template: RequestTemplate,
+    max_concurrency: usize,
+}
+
+impl FanOut {
+    /// Builds a child request per item of the parent bodies, skipping items that fail to render
+    fn requests(
+        &self,
+        client: &Client,
+        bodies: &[Bytes],
+        vars: &TemplateVars,
+    ) -> Vec<(Value, Request)> {
+        let mut requests = Vec::new();
+
+        for body in bodies {
+            let items = self
+                .items
+                .select_values(&String::from_utf8_lossy(body))
+                .unwrap_or_else(|err| {
+                    error!("Fan-out item selection failed: {err:?}");
+                    vec![]
+                });
+
+            for item in items {
+                let vars = TemplateVars {
+                    item: Some(item.clone()),
+                    ..vars.clone()
+                };
+                match self.template.build(client, &vars) {
+                    Ok(request) => requests.push((item, request)),
+                    Err(err) => error!("Unable to build fan-out request for {item}: {err:?}"),
+                }
+            }
+        }
+
+        requests
+    }
+}
+
+/// Produces the records of a single tick, following pages when pagination is configured.
+fn poll(
+    client: Client,
+    template: RequestTemplate,
+    options: PollOptions,
+    state: Arc<Mutex<PollState>>,
+    auth: Option<Authenticator>,
+    dead_letter: Option<DeadLetterQueue>,
+    formatter: Arc<dyn Formatter + Sync + Send>,
+) -> LocalBoxStream<'static, Result<SourceRecord>> {
+    Box::pin(async_stream::stream! {
+        let mut vars = template.tick();
+        let resume = lock(&state)
+            .pagination
+            .take()
+            .filter(|_| options.pagination.is_some());
+        if let Some(ref resume) = resume {
+            debug!("Resuming pagination from checkpoint");
+            vars.now = resume.tick_time;
+        }
+        let mut first_page = resume.is_none();
+        let mut page = resume.as_ref().map_or(0, |resume| resume.page);
+
+        let request = match template.build(&client, &vars) {
+            Ok(request) => request,
+            Err(err) => {
+                error!("Request execution failed: {err:?}");
+                return;
+            }
+        };
+
+        let mut paginator = options.pagination.clone().map(|pagination| match resume {
+            Some(resume) => Paginator::resume(pagination, resume.paginator, request.url()),
+            None => Paginator::new(pagination, request.url()),
+        });
+        loop {
+            let Some(mut page_request) = request.try_clone() else {
+                error!("Request execution failed: Request must be cloneable");
+                break;
+            };
+            if let Some(ref paginator) = paginator {
+                *page_request.url_mut() = paginator.current_url().clone();
+            }
+            if first_page && options.conditional_requests {
+                lock(&state).apply_conditional_headers(&mut page_request);
+            }
+            let page_url = page_request.url().to_string();
+
+            let response = fetch(
+                &client,
+                page_request,
+                auth.as_ref(),
+                &options,
+                first_page && options.conditional_requests,
+                dead_letter.as_ref(),
+            )
+            .await;
+            let (metadata, body) = match response {
+                Ok(Some(page)) => page,
+                Ok(None) => break,
+                Err(err) if is_fatal(&err) => {
+                    yield Err(err);
+                    break;
+                }
+                Err(err) => {
+                    error!("Request execution failed: {err:?}");
+                    if let Some(ref dead_letter) = dead_letter {
+                        dead_letter.send(DeadLetter::request_failed(page_url, &err)).await;
                     }
-                    Err(err) => {
-                        error!("Request execution failed: {err:?}");
+                    break;
+                }
+            };
 
-                        None
+            if first_page && options.conditional_requests {
+                if metadata.status_code == Some(StatusCode::NOT_MODIFIED.as_u16()) {
+                    debug!("Resource not modified since previous request, skipping");
+                    template.succeeded(&vars);
+                    break;
+                }
+                lock(&state).update_validators(vars.now, &metadata);
+            }
+            first_page = false;
+
+            let duplicate = options.dedup && lock(&state).is_duplicate(vars.now, page, &body);
+            page += 1;
+
+            let (has_next, bodies) = match paginator.as_mut() {
+                Some(paginator) => {
+                    let text = String::from_utf8_lossy(&body);
+                    let has_next = paginator.advance(&metadata, &text).unwrap_or_else(|err| {
+                        error!("Pagination failed: {err:?}");
+                        false
+                    });
+                    let records = paginator.records(text.into_owned());
+                    (has_next, records.into_iter().map(Bytes::from).collect())
+                }
+                None => (false, vec![body]),
+            };
+
+            let bodies = if duplicate {
+                debug!("Response body is identical to previous response, skipping");
+                vec![]
+            } else {
+                bodies
+            };
+
+            let records = match options.fan_out {
+                Some(ref fan_out) => {
+                    let children = fan_out.requests(&client, &bodies, &vars);
+                    fan_out_records(
+                        &client,
+                        children,
+                        fan_out.max_concurrency,
+                        &options,
+                        auth.as_ref(),
+                        dead_letter.as_ref(),
+                        formatter.as_ref(),
+                    )
+                    .await
+                }
+                None => Ok(page_records(
+                    bodies,
+                    &metadata,
+                    &page_url,
+                    None,
+                    &options,
+                    formatter.as_ref(),
+                    dead_letter.as_ref(),
+                )
+                .await),
+            };
+            let mut records = match records {
+                Ok(records) => records,
+                Err(err) => {
+                    yield Err(err);
+                    break;
+                }
+            };
+            if options.checkpoint {
+                if let Some(record) = records.last_mut() {
+                    let next_page = paginator.as_ref().filter(|_| has_next);
+                    record.checkpoint = Some(Box::new(checkpoint(&state, &template, next_page, page, &vars)));
+                }
+            }
+            for record in records {
+                yield Ok(record);
+            }
+
+            if !has_next {
+                template.succeeded(&vars);
+                break;
+            }
+        }
+    })
+}
+
+/// Sends the fan-out child requests, at most `max_concurrency` at a time, and formats their
+/// responses with the parent item attached.
+async fn fan_out_records(
+    client: &Client,
+    children: Vec<(Value, Request)>,
+    max_concurrency: usize,
+    options: &PollOptions,
+    auth: Option<&Authenticator>,
+    dead_letter: Option<&DeadLetterQueue>,
+    formatter: &(dyn Formatter + Sync + Send),
+) -> Result<Vec<SourceRecord>> {
+    let responses: Vec<_> = futures::stream::iter(children)
+        .map(|(item, request)| async move {
+            let url = request.url().to_string();
+            let response = fetch(client, request, auth, options, false, dead_letter).await;
+            (item, url, response)
+        })
+        .buffered(max_concurrency)
+        .collect()
+        .await;
+
+    let mut records = Vec::new();
+    for (item, url, response) in responses {
+        match response {
+            Ok(Some((metadata, body))) => {
+                let child_records = page_records(
+                    vec![body],
+                    &metadata,
+                    &url,
+                    Some(&item),
+                    options,
+                    formatter,
+                    dead_letter,
+                )
+                .await;
+                records.extend(child_records);
+            }
+            Ok(None) => {}
+            Err(err) if is_fatal(&err) => return Err(err),
+            Err(err) => {
+                error!("Fan-out request failed: {err:?}");
+                if let Some(dead_letter) = dead_letter {
+                    dead_letter
+                        .send(DeadLetter::request_failed(url, &err))
+                        .await;
+                }
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+/// Formats the records of a page, forwarding records that fail to format to the dead letter topic.
+async fn page_records(
+    bodies: Vec<Bytes>,
+    metadata: &HttpResponseMetadata,
+    page_url: &str,
+    parent: Option<&Value>,
+    options: &PollOptions,
+    formatter: &(dyn Formatter + Sync + Send),
+    dead_letter: Option<&DeadLetterQueue>,
+) -> Vec<SourceRecord> {
+    let mut page_records = Vec::new();
+
+    for body in bodies {
+        let records = match options.record_selector {
+            Some(ref selector) => selector
+                .select(&String::from_utf8_lossy(&body))
+                .map(|records| records.into_iter().map(Bytes::from).collect())
+                .unwrap_or_else(|err| {
+                    error!("Record selection failed: {err:?}");
+                    vec![]
+                }),
+            None => vec![body],
+        };
+
+        for record in records {
+            let response = HttpResponseRecord::new(metadata.clone(), record.clone())
+                .with_parent(parent.cloned());
+            match formatter.format(&response) {
+                Ok(res) => {
+                    trace!(
+                        "Request execution completed: {}",
+                        String::from_utf8_lossy(&res)
+                    );
+
+                    page_records.push(SourceRecord::new(
+                        options.record_key.as_ref(),
+                        metadata,
+                        &record,
+                        res,
+                    ));
+                }
+                Err(err) => {
+                    error!("Error formatting record: {err:?}");
+                    if let Some(dead_letter) = dead_letter {
+                        let letter = DeadLetter::new(
+                            DeadLetterKind::FormatFailed,
+                            page_url,
+                            metadata,
+                            &record,
+                        )
+                        .with_error(&err);
+                        dead_letter.send(letter).await;
                     }
                 }
             }
+        }
+    }
+
+    page_records
+}
+
+/// State after a page, resuming at `next_page` or starting the next tick when it is `None`.
+///
+/// A tick older than the latest successful one is not resumed and never moves
+/// `last_success_time` back, since concurrent ticks complete out of order.
+fn checkpoint(
+    state: &Mutex<PollState>,
+    template: &RequestTemplate,
+    next_page: Option<&Paginator>,
+    page: usize,
+    vars: &TemplateVars,
+) -> Checkpoint {
+    let completed = template.last_success_time();
+    let next_page = next_page.filter(|_| vars.now > completed);
+    let mut poll = lock(state).clone();
+    poll.pagination = next_page
+        .and_then(Paginator::state)
+        .map(|paginator| PaginationResume {
+            tick_time: vars.now,
+            page,
+            paginator: paginator.clone(),
         });
+    let last_success_time = match next_page {
+        Some(_) => vars.last_success_time.max(completed),
+        None => vars.now.max(completed),
+    };
 
-        Ok(stream.boxed_local())
+    Checkpoint {
+        poll,
+        last_success_time: Some(last_success_time),
     }
 }
 
-async fn request(builder: Option<RequestBuilder>, formatter: &dyn Formatter) -> Result<String> {
-    let request = builder.ok_or_else(|| anyhow!("Request must be cloneable"))?;
+/// Sends a request, retrying failures with the retry policy, and applies the status policy,
+/// returning `None` for rejected responses.
+async fn fetch(
+    client: &Client,
+    request: Request,
+    auth: Option<&Authenticator>,
+    options: &PollOptions,
+    accept_not_modified: bool,
+    dead_letter: Option<&DeadLetterQueue>,
+) -> Result<Option<(HttpResponseMetadata, Bytes)>> {
+    let endpoint = request.url().to_string();
+    let status_policy = options.status_policy.as_ref();
+    let retry = options.retry.as_ref();
+    let max_retries = status_policy.map_or(0, |policy| policy.max_retries);
+    // rejected statuses are retried up to the status policy max_retries
+    let mut backoff = Backoff::new(&RetryPolicy {
+        max_attempts: None,
+        ..retry.cloned().unwrap_or_default()
+    });
+    let mut request_backoff = retry.map(|retry| Backoff::new(&retry.per_request()));
+    let mut retries = 0;
 
-    let response = request.send().await.context("send request")?;
-    let response_metadata = HttpResponseMetadata::new(&response)?;
-    let body = response
-        .text()
+    loop {
+        let attempt = request.try_clone().context("request must be cloneable")?;
+        let (metadata, body) = match send(client, attempt, auth, options.body_format).await {
+            Ok(response) => response,
+            Err(err) => {
+                let Some(ref mut request_backoff) = request_backoff else {
+                    return Err(err);
+                };
+                match request_backoff.next() {
+                    Some(wait) => {
+                        warn!(
+                            "Request to {endpoint} failed: {err:#}, retrying in {}",
+                            humantime::format_duration(wait)
+                        );
+                        async_std::task::sleep(wait).await;
+                        continue;
+                    }
+                    // the default bound ends the tick instead of stopping the connector
+                    None if retry.is_some_and(|retry| retry.max_attempts.is_some())
+                        && request_backoff.policy().on_give_up == GiveUpAction::Exit =>
+                    {
+                        let attempts = request_backoff.failures();
+                        return Err(err.context(RetriesExhausted { attempts }));
+                    }
+                    None => return Err(err),
+                }
+            }
+        };
+        let status = metadata.status_code.unwrap_or_default();
+
+        let action = status_policy
+            .and_then(|policy| policy.check(status))
+            .filter(|_| !(accept_not_modified && status == StatusCode::NOT_MODIFIED.as_u16()));
+        let Some(action) = action else {
+            return Ok(Some((metadata, body)));
+        };
+
+        match action {
+            StatusAction::Retry if retries < max_retries => {
+                retries += 1;
+                let wait = retry_after(&metadata, SystemTime::now())
+                    .map(|wait| wait.min(backoff.policy().max_delay))
+                    .or_else(|| backoff.next())
+                    .unwrap_or_default();
+                warn!(
+                    "{endpoint} responded with status {status}, retrying in {}",
+                    humantime::format_duration(wait)
+                );
+                async_std::task::sleep(wait).await;
+            }
+            StatusAction::Retry => {
+                error!(
+                    "{endpoint} responded with status {status}, giving up after {retries} retries"
+                );
+                return Ok(None);
+            }
+            StatusAction::Drop => {
+                warn!("{endpoint} responded with status {status}, dropping response");
+                return Ok(None);
+            }
+            StatusAction::DeadLetter => {
+                if let Some(dead_letter) = dead_letter {
+                    dead_letter
+                        .send(DeadLetter::new(
+                            DeadLetterKind::RejectedStatus,
+                            endpoint,
+                            &metadata,
+                            &body,
+                        ))
+                        .await;
+                }
+                return Ok(None);
+            }
+            StatusAction::Fail => return Err(RejectedStatus { status, endpoint }.into()),
+        }
+    }
+}
+
+async fn execute_authorized(
+    client: &Client,
+    mut request: Request,
+    auth: &Authenticator,
+) -> Result<Response> {
+    auth.authorize(&mut request)
+        .await
+        .context("authorize request")?;
+
+    execute(client, request).await
+}
+
+/// Sends a request, leaving the URL out of errors since it may carry an `api_key` query parameter
+async fn execute(client: &Client, request: Request) -> Result<Response> {
+    client
+        .execute(request)
         .await
-        .context("read response body as text")?;
+        .map_err(reqwest::Error::without_url)
+        .context("send request")
+}
+
+fn lock(state: &Mutex<PollState>) -> std::sync::MutexGuard<'_, PollState> {
+    state
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+async fn send(
+    client: &Client,
+    request: Request,
+    auth: Option<&Authenticator>,
+    body_format: BodyFormat,
+) -> Result<(HttpResponseMetadata, Bytes)> {
+    let response = match auth {
+        Some(auth) => {
+            let retry = request.try_clone();
+            let response = execute_authorized(client, request, auth).await?;
+
+            match retry {
+                Some(retry) if response.status() == StatusCode::UNAUTHORIZED => {
+                    debug!("Credentials rejected, retrying with new credentials");
+                    auth.invalidate().await;
+                    execute_authorized(client, retry, auth).await?
+                }
+                _ => response,
+            }
+        }
+        None => execute(client, request).await?,
+    };
+    let response_metadata = HttpResponseMetadata::new(&response)?;
+    let body = match body_format {
+        BodyFormat::Text => response
+            .text()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .context("read response body as text")?
+            .into(),
+        BodyFormat::Raw | BodyFormat::Base64 => response
+            .bytes()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .context("read response body")?,
+    };
+
+    Ok((response_metadata, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant},
+    };
 
-    let record_payload = HttpResponseRecord::new(response_metadata, body);
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
 
-    formatter.to_string(&record_payload)
+    use crate::config::AuthConfig;
+
+    use super::*;
+
+    /// Local HTTP server answering the request number `n` with body `n` after `delay(n)`
+    struct SlowServer {
+        address: SocketAddr,
+        started: Instant,
+        starts: Arc<Mutex<Vec<Instant>>>,
+        max_active: Arc<AtomicUsize>,
+    }
+
+    impl SlowServer {
+        async fn spawn(delay: fn(usize) -> Duration) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let starts = Arc::new(Mutex::new(Vec::new()));
+            let max_active = Arc::new(AtomicUsize::new(0));
+            let active = Arc::new(AtomicUsize::new(0));
+
+            let server = Self {
+                address,
+                started: Instant::now(),
+                starts: starts.clone(),
+                max_active: max_active.clone(),
+            };
+            async_std::task::spawn(async move {
+                loop {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+                    let (starts, max_active, active) =
+                        (starts.clone(), max_active.clone(), active.clone());
+                    async_std::task::spawn(async move {
+                        let mut request = Vec::new();
+                        let mut buf = [0; 1024];
+                        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                            match stream.read(&mut buf).await {
+                                Ok(0) | Err(_) => return,
+                                Ok(read) => request.extend_from_slice(&buf[..read]),
+                            }
+                        }
+                        let n = {
+                            let mut starts = starts.lock().unwrap();
+                            starts.push(Instant::now());
+                            starts.len() - 1
+                        };
+                        let running = active.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_active.fetch_max(running, Ordering::SeqCst);
+
+                        async_std::task::sleep(delay(n)).await;
+
+                        active.fetch_sub(1, Ordering::SeqCst);
+                        let body = n.to_string();
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                            body.len()
+                        );
+                        let _ = stream.write_all(response.as_bytes()).await;
+                    });
+                }
+            });
+
+            server
+        }
+
+        /// Time of each request since the server was started
+        fn request_times(&self) -> Vec<Duration> {
+            let starts = self.starts.lock().unwrap();
+            starts.iter().map(|start| *start - self.started).collect()
+        }
+    }
+
+    /// Values of the records polled from `server` during `window`
+    async fn records_during(server: &SlowServer, settings: &str, window: Duration) -> Vec<String> {
+        let config: HttpConfig = serde_json::from_str(&format!(
+            r#"{{"endpoint": "http://{}", {settings}}}"#,
+            server.address
+        ))
+        .unwrap();
+        let context = SourceContext::new(&config, None, None, None).await.unwrap();
+        let stream = HttpSource::new(&config, &context)
+            .unwrap()
+            .connect(None)
+            .await
+            .unwrap();
+
+        stream
+            .take_until(async_std::task::sleep(window))
+            .map(|record| String::from_utf8(record.unwrap().value).unwrap())
+            .collect()
+            .await
+    }
+
+    #[async_std::test]
+    async fn test_max_in_flight_limits_concurrent_ticks() {
+        for max_in_flight in [1, 3] {
+            let server = SlowServer::spawn(|_| Duration::from_millis(500)).await;
+
+            let records = records_during(
+                &server,
+                &format!(r#""interval": "50ms", "max_in_flight": {max_in_flight}"#),
+                Duration::from_millis(1600),
+            )
+            .await;
+
+            let max_active = server.max_active.load(Ordering::SeqCst);
+            assert!(max_active <= max_in_flight, "{max_active}");
+            assert!(max_active >= max_in_flight.min(2), "{max_active}");
+            assert!(records.len() > max_in_flight, "{records:?}");
+        }
+    }
+
+    #[async_std::test]
+    async fn test_missed_tick_while_tick_runs() {
+        // the first tick takes 2100ms, so the ticks due at 600ms, 1200ms and 1800ms are missed
+        let times_after_slow_tick = |missed_tick: &'static str| async move {
+            let server =
+                SlowServer::spawn(|n| Duration::from_millis(if n == 0 { 2100 } else { 0 })).await;
+            records_during(
+                &server,
+                &format!(
+                    r#""interval": "600ms", "max_in_flight": 1, "missed_tick": "{missed_tick}""#
+                ),
+                Duration::from_millis(3200),
+            )
+            .await;
+
+            let times = server.request_times();
+            assert!(times.len() >= 3, "{times:?}");
+            // no tick is started while the slow one runs
+            assert!(times[1] >= Duration::from_millis(2100), "{times:?}");
+            times
+        };
+        let (burst, skip, delay) = futures::join!(
+            times_after_slow_tick("burst"),
+            times_after_slow_tick("skip"),
+            times_after_slow_tick("delay")
+        );
+
+        // missed ticks are sent right away
+        assert!(
+            burst[2] - burst[1] < Duration::from_millis(150),
+            "{burst:?}"
+        );
+        // the next tick is the 2400ms one
+        assert!(skip[2] >= Duration::from_millis(2400), "{skip:?}");
+        assert!(skip[2] - skip[1] < Duration::from_millis(450), "{skip:?}");
+        // the next tick is a full interval after the missed one
+        assert!(
+            delay[2] - delay[1] >= Duration::from_millis(500),
+            "{delay:?}"
+        );
+    }
+
+    #[async_std::test]
+    async fn test_timeout_aborts_slow_request() {
+        let server = SlowServer::spawn(|n| Duration::from_secs(if n == 0 { 5 } else { 0 })).await;
+
+        let records = records_during(
+            &server,
+            r#""interval": "100ms", "max_in_flight": 1, "timeout": "200ms""#,
+            Duration::from_millis(1000),
+        )
+        .await;
+
+        // the first request is aborted and the following ticks are not held back by it
+        assert_eq!(records.first().map(String::as_str), Some("1"));
+        assert!(!records.contains(&"0".to_string()));
+    }
+
+    #[async_std::test]
+    async fn test_failed_request_hides_query_key() {
+        let api_key: AuthConfig = serde_json::from_str(
+            r#"{"type":"api_key","name":"key","value":"s3cret","in":"query"}"#,
+        )
+        .unwrap();
+        let client = Client::new();
+        let auth = Authenticator::new(&api_key, &client, None).unwrap();
+        // nothing listens on port 1, so the request fails after the key was added
+        let request = client.get("http://127.0.0.1:1/items").build().unwrap();
+
+        let err = send(&client, request, Some(&auth), BodyFormat::Text)
+            .await
+            .unwrap_err();
+        assert!(!format!("{err:?}").contains("s3cret"));
+
+        let letter = DeadLetter::request_failed("http://127.0.0.1:1/items", &err);
+        assert!(!letter.error.unwrap().contains("s3cret"));
+    }
 }
@@ -0,0 +1,225 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const DEFAULT_EVENT_TYPE: &str = "message";
+
+/// A dispatched `text/event-stream` event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SseEvent {
+    pub event: String,
+    pub id: Option<String>,
+    pub data: String,
+}
+
+/// Connection state that outlives a single stream, shared with the reconnect loop.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SseState(Arc<Mutex<SseStateInner>>);
+
+#[derive(Debug, Default)]
+struct SseStateInner {
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseState {
+    /// Value to send in the `Last-Event-ID` header when reconnecting
+    pub(crate) fn last_event_id(&self) -> Option<String> {
+        self.lock().last_event_id.clone()
+    }
+
+    /// Reconnection time requested by the server with the `retry` field
+    pub(crate) fn retry(&self) -> Option<Duration> {
+        self.lock().retry
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SseStateInner> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Incremental `text/event-stream` parser, see
+/// https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation
+pub(crate) struct SseParser {
+    state: SseState,
+    buf: Vec<u8>,
+    data: String,
+    event: String,
+    id: Option<String>,
+}
+
+impl SseParser {
+    pub(crate) fn new(state: SseState) -> Self {
+        let id = state.last_event_id();
+        Self {
+            state,
+            buf: Vec::new(),
+            data: String::new(),
+            event: String::new(),
+            id,
+        }
+    }
+
+    /// Consumes a chunk of the response and returns the events completed by it.
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some((line_end, next_line)) = self.next_line_end() {
+            let line = String::from_utf8_lossy(&self.buf[..line_end]).into_owned();
+            self.buf.drain(..next_line);
+
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+
+    /// Finds the end of the first complete line, accepting CRLF, LF and CR line endings.
+    fn next_line_end(&self) -> Option<(usize, usize)> {
+        let index = self.buf.iter().position(|b| *b == b'\n' || *b == b'\r')?;
+        if self.buf[index] == b'\n' {
+            return Some((index, index + 1));
+        }
+
+        match self.buf.get(index + 1) {
+            Some(b'\n') => Some((index, index + 2)),
+            Some(_) => Some((index, index + 1)),
+            // a CR at the end of the buffer may be followed by LF in the next chunk
+            None => None,
+        }
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(millis) = value.parse::<u64>() {
+                    self.state.lock().retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        self.state.lock().last_event_id.clone_from(&self.id);
+
+        let event = std::mem::take(&mut self.event);
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+
+        Some(SseEvent {
+            event: if event.is_empty() {
+                DEFAULT_EVENT_TYPE.to_string()
+            } else {
+                event
+            },
+            id: self.id.clone(),
+            data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event: &str, id: Option<&str>, data: &str) -> SseEvent {
+        SseEvent {
+            event: event.to_string(),
+            id: id.map(str::to_string),
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parses_events() {
+        let mut parser = SseParser::new(SseState::default());
+
+        let events = parser.feed(b"event:get request(s)\ndata:{ \"gets\": 1 }\n\ndata: second\n\n");
+
+        assert_eq!(
+            events,
+            vec![
+                event("get request(s)", None, "{ \"gets\": 1 }"),
+                event("message", None, "second"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_joins_multi_line_data_and_skips_comments() {
+        let mut parser = SseParser::new(SseState::default());
+
+        let events = parser.feed(b": keep-alive\ndata: line 1\ndata\ndata: line 3\n\n");
+
+        assert_eq!(events, vec![event("message", None, "line 1\n\nline 3")]);
+    }
+
+    #[test]
+    fn test_handles_events_split_across_chunks() {
+        let mut parser = SseParser::new(SseState::default());
+
+        assert!(parser.feed(b"id: 1\r").is_empty());
+        assert!(parser.feed(b"\ndata: hel").is_empty());
+        assert!(parser.feed(b"lo\r\n").is_empty());
+        let events = parser.feed(b"\r\n");
+
+        assert_eq!(events, vec![event("message", Some("1"), "hello")]);
+    }
+
+    #[test]
+    fn test_updates_shared_state() {
+        let state = SseState::default();
+        let mut parser = SseParser::new(state.clone());
+
+        parser.feed(b"retry: 3000\nid: 41\ndata: a\n\nid: 42\n\n");
+
+        assert_eq!(state.retry(), Some(Duration::from_millis(3000)));
+        assert_eq!(state.last_event_id(), Some("42".to_string()));
+
+        let mut parser = SseParser::new(state);
+        let events = parser.feed(b"data: b\n\n");
+        assert_eq!(events, vec![event("message", Some("42"), "b")]);
+    }
+
+    #[test]
+    fn test_ignores_invalid_retry() {
+        let state = SseState::default();
+        let mut parser = SseParser::new(state.clone());
+
+        parser.feed(b"retry: soon\n\n");
+
+        assert_eq!(state.retry(), None);
+    }
+}
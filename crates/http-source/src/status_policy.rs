@@ -0,0 +1,180 @@
+use std::{
+    fmt,
+    ops::RangeInclusive,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use reqwest::{header::RETRY_AFTER, StatusCode};
+use serde::Deserialize;
+
+use crate::formatter::HttpResponseMetadata;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Decides which response statuses are published and what happens to the others.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct StatusPolicy {
+    /// Accepted status codes or ranges, e.g. `200-299` or `304`
+    #[serde(default = "default_accept")]
+    pub accept: Vec<StatusRange>,
+
+    /// Action for responses with any other status: drop | retry | dead_letter | fail
+    #[serde(default = "Default::default")]
+    pub on_reject: StatusAction,
+
+    /// Retries of a rejected request before it is dropped, used by the retry action
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StatusAction {
+    /// Log and discard the response
+    #[default]
+    Drop,
+    /// Send the request again after a backoff or the `Retry-After` delay
+    Retry,
+    /// Forward the response to the dead letter topic
+    DeadLetter,
+    /// Stop the connector with an error
+    Fail,
+}
+
+/// An inclusive range of status codes, written as `200-299` or a single code.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "StatusRangeRepr")]
+pub(crate) struct StatusRange(RangeInclusive<u16>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StatusRangeRepr {
+    Code(u16),
+    Range(String),
+}
+
+impl TryFrom<StatusRangeRepr> for StatusRange {
+    type Error = anyhow::Error;
+
+    fn try_from(repr: StatusRangeRepr) -> Result<Self> {
+        match repr {
+            StatusRangeRepr::Code(code) => Ok(Self(code..=code)),
+            StatusRangeRepr::Range(range) => Self::parse(&range),
+        }
+    }
+}
+
+impl StatusRange {
+    fn parse(range: &str) -> Result<Self> {
+        let invalid = || anyhow!("invalid status range \"{range}\", expected e.g. \"200-299\"");
+
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let start = start.trim().parse().map_err(|_| invalid())?;
+        let end = end.trim().parse().map_err(|_| invalid())?;
+        if start > end {
+            return Err(invalid());
+        }
+
+        Ok(Self(start..=end))
+    }
+}
+
+impl StatusPolicy {
+    /// Returns the action for a response status, or `None` when it is accepted
+    pub(crate) fn check(&self, status: u16) -> Option<StatusAction> {
+        if self.accept.iter().any(|range| range.0.contains(&status)) {
+            None
+        } else {
+            Some(self.on_reject)
+        }
+    }
+}
+
+/// Delay requested by the server with `Retry-After` on 429 and 503 responses
+pub(crate) fn retry_after(metadata: &HttpResponseMetadata, now: SystemTime) -> Option<Duration> {
+    let status = metadata.status_code?;
+    if status != StatusCode::TOO_MANY_REQUESTS.as_u16()
+        && status != StatusCode::SERVICE_UNAVAILABLE.as_u16()
+    {
+        return None;
+    }
+
+    let value = metadata.header(RETRY_AFTER.as_str())?.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()
+            .map(|date| date.duration_since(now).unwrap_or_default()),
+    }
+}
+
+/// Error returned when a response is rejected by a `fail` status policy
+#[derive(Debug)]
+pub(crate) struct RejectedStatus {
+    pub status: u16,
+    pub endpoint: String,
+}
+
+impl fmt::Display for RejectedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} responded with rejected status {}",
+            self.endpoint, self.status
+        )
+    }
+}
+
+impl std::error::Error for RejectedStatus {}
+
+fn default_accept() -> Vec<StatusRange> {
+    vec![StatusRange(200..=299)]
+}
+
+fn default_max_retries() -> u32 {
+    DEFAULT_MAX_RETRIES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::HttpHeader;
+
+    #[test]
+    fn test_check_accepted_ranges() {
+        let policy: StatusPolicy =
+            serde_json::from_str(r#"{"accept":["200-299",304],"on_reject":"retry"}"#).unwrap();
+
+        assert_eq!(policy.check(200), None);
+        assert_eq!(policy.check(304), None);
+        assert_eq!(policy.check(404), Some(StatusAction::Retry));
+        assert_eq!(policy.max_retries, DEFAULT_MAX_RETRIES);
+
+        assert!(serde_json::from_str::<StatusPolicy>(r#"{"accept":["299-200"]}"#).is_err());
+        assert!(serde_json::from_str::<StatusPolicy>(r#"{"accept":["2xx"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        let metadata = |status, value: &str| HttpResponseMetadata {
+            status_code: Some(status),
+            headers: Some(vec![HttpHeader {
+                name: "retry-after".into(),
+                value: value.into(),
+            }]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            retry_after(&metadata(429, "120"), now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            retry_after(&metadata(503, "Wed, 21 Oct 2015 07:29:00 GMT"), now),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(retry_after(&metadata(500, "120"), now), None);
+    }
+}
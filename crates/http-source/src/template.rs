@@ -0,0 +1,123 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+const OPEN: &str = "{{";
+const CLOSE: &str = "}}";
+const ESCAPE: char = '\\';
+
+/// A string with `{{ expression }}` placeholders, rendered by resolving each expression.
+///
+/// `\{{` is a literal `{{`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Expression(String),
+}
+
+impl TryFrom<String> for Template {
+    type Error = anyhow::Error;
+
+    fn try_from(template: String) -> Result<Self> {
+        Self::parse(&template)
+    }
+}
+
+impl Template {
+    pub(crate) fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find(OPEN) {
+            if let Some(before) = rest[..start].strip_suffix(ESCAPE) {
+                literal.push_str(before);
+                literal.push_str(OPEN);
+                rest = &rest[start + OPEN.len()..];
+                continue;
+            }
+
+            literal.push_str(&rest[..start]);
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+
+            let after_open = &rest[start + OPEN.len()..];
+            let end = after_open
+                .find(CLOSE)
+                .ok_or_else(|| anyhow!("unclosed \"{OPEN}\" in template \"{template}\""))?;
+            let expression = after_open[..end].trim();
+            if expression.is_empty() {
+                return Err(anyhow!("empty expression in template \"{template}\""));
+            }
+
+            parts.push(Part::Expression(expression.to_string()));
+            rest = &after_open[end + CLOSE.len()..];
+        }
+
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+
+    pub(crate) fn render(&self, mut resolve: impl FnMut(&str) -> Result<String>) -> Result<String> {
+        let mut rendered = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => rendered.push_str(literal),
+                Part::Expression(expression) => rendered.push_str(&resolve(expression)?),
+            }
+        }
+
+        Ok(rendered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_replaces_expressions() {
+        let template = Template::parse("{{ a }}-static-{{b}}").unwrap();
+
+        let rendered = template
+            .render(|expression| Ok(expression.to_uppercase()))
+            .unwrap();
+
+        assert_eq!(rendered, "A-static-B");
+    }
+
+    #[test]
+    fn test_escaped_open_is_literal() {
+        let template = Template::parse(r#"{"a": \{{ b }}, "c": "{{ d }}\{{"}"#).unwrap();
+
+        let rendered = template
+            .render(|expression| Ok(expression.to_uppercase()))
+            .unwrap();
+
+        assert_eq!(rendered, r#"{"a": {{ b }}, "c": "D{{"}"#);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_templates() {
+        assert!(Template::parse("{{ a").is_err());
+        assert!(Template::parse("{{ }}").is_err());
+        assert_eq!(
+            Template::parse("no placeholders")
+                .unwrap()
+                .render(|_| Ok(String::new()))
+                .unwrap(),
+            "no placeholders"
+        );
+    }
+}
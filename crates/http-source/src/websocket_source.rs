@@ -1,4 +1,6 @@
-use anyhow::{Context, Result};
+use std::{sync::Arc, time::SystemTime};
+
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use fluvio::Offset;
 use fluvio_connector_common::{
@@ -10,37 +12,96 @@ use futures::{
     stream::{LocalBoxStream, SplitSink},
     SinkExt,
 };
+use serde_json::Value;
 use tokio::net::TcpStream;
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
 use tokio_stream::{wrappers::IntervalStream, StreamExt};
 use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{client::IntoClientRequest, protocol::Message},
-    MaybeTlsStream, WebSocketStream,
+    client_async_tls_with_config, connect_async_tls_with_config,
+    tungstenite::{
+        client::IntoClientRequest,
+        error::CapacityError,
+        protocol::{Message, WebSocketConfig as ProtocolConfig},
+        Error as WsError,
+    },
+    Connector, MaybeTlsStream, WebSocketStream,
 };
-use url::Url;
 
-use crate::config::HttpConfig;
+use crate::{
+    auth::Authenticator,
+    config::{AckConfig, HttpConfig, MessagePattern, OversizedMessageAction, RecordKeyConfig},
+    context::SourceContext,
+    dead_letter::{DeadLetter, DeadLetterKind, DeadLetterQueue},
+    formatter::{
+        formatter, Formatter, FrameMetadata, FrameOpcode, HttpResponseMetadata, HttpResponseRecord,
+    },
+    proxy::ProxySettings,
+    record::SourceRecord,
+    request_template::{RequestTemplate, TemplateVars},
+    template::Template,
+    tls,
+};
 
 pub(crate) struct WebSocketSource {
     request: WSRequest,
     ping_interval_ms: u64,
+    pong_timeout: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    record_key: Option<RecordKeyConfig>,
+    formatter: Arc<dyn Formatter + Sync + Send>,
+    on_oversized_message: OversizedMessageAction,
+    dead_letter: Option<DeadLetterQueue>,
+    auth: Option<Authenticator>,
+    template: RequestTemplate,
+    vars: TemplateVars,
 }
 
 #[derive(Clone)]
 struct WSRequest {
     request: tokio_tungstenite::tungstenite::handshake::client::Request,
     subscription_messages: Vec<String>,
+    /// Rendered `auth_message`
+    auth_message: Option<String>,
+    await_ack: Option<AckConfig>,
+    /// Sent with every ping
+    keepalive_message: Option<String>,
+    heartbeat_reply: Option<MessagePattern>,
+    /// Custom `tls` settings, the default roots are used when unset
+    connector: Option<Connector>,
+    /// `wss://` and `ws://` endpoints are reached through an HTTP CONNECT tunnel
+    proxy: Option<Arc<ProxySettings>>,
+    /// Message and frame size limits, enforced by tungstenite while reading
+    protocol: ProtocolConfig,
 }
 
 type Transport = MaybeTlsStream<TcpStream>;
 
+/// Frame read from the connection, after ping frames are answered
+enum Frame {
+    Record(SourceRecord),
+    Pong,
+    /// Message or frame above the size limits, its payload is discarded and the connection reopened
+    Oversized {
+        size: usize,
+        max_size: usize,
+        metadata: HttpResponseMetadata,
+    },
+    /// Message that could not be formatted, with its dead letter
+    FormatFailed(DeadLetter),
+    /// Close frame or read error, the connection is reopened
+    Closed,
+}
+
 #[async_trait]
 trait PingStream {
     async fn ping(&mut self) -> Result<()>;
 }
 
-struct WSPingOnlySink(SplitSink<WebSocketStream<Transport>, Message>);
+/// Write half of the connection, with the `keepalive_message` sent after every ping frame
+struct WSPingOnlySink(
+    SplitSink<WebSocketStream<Transport>, Message>,
+    Option<String>,
+);
 
 #[async_trait]
 impl PingStream for WSPingOnlySink {
@@ -49,108 +110,261 @@ impl PingStream for WSPingOnlySink {
             error!("Failed to send ping: {}", e);
             anyhow::Error::new(e)
         })?;
+        if let Some(ref keepalive) = self.1 {
+            self.0
+                .send(Message::Text(keepalive.clone()))
+                .await
+                .map_err(|e| {
+                    error!("Failed to send keepalive message: {}", e);
+                    anyhow::Error::new(e)
+                })?;
+        }
 
         debug!("Ping sent");
         Ok(())
     }
 }
 
-async fn establish_connection(request: WSRequest) -> Result<WebSocketStream<Transport>> {
-    match connect_async(request.request.clone()).await {
-        Ok((mut ws_stream, _)) => {
+async fn establish_connection(
+    request: WSRequest,
+    auth: Option<&Authenticator>,
+) -> Result<(WebSocketStream<Transport>, HttpResponseMetadata)> {
+    let mut handshake = request.request.clone();
+    if let Some(auth) = auth {
+        auth.authorize_http(&mut handshake)
+            .await
+            .context("authorize WebSocket handshake")?;
+    }
+
+    let uri = handshake.uri().clone();
+    let host = uri.host().unwrap_or_default();
+    let proxy = request.proxy.as_ref().filter(|proxy| !proxy.bypass(host));
+    let connection = match proxy {
+        Some(proxy) => {
+            let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                Some("wss") => 443,
+                _ => 80,
+            });
+            let stream = proxy
+                .tunnel(host, port)
+                .await
+                .context("unable to reach WebSocket endpoint through proxy")?;
+            client_async_tls_with_config(
+                handshake,
+                stream,
+                Some(request.protocol),
+                request.connector.clone(),
+            )
+            .await
+        }
+        None => {
+            connect_async_tls_with_config(
+                handshake,
+                Some(request.protocol),
+                false,
+                request.connector.clone(),
+            )
+            .await
+        }
+    };
+
+    match connection {
+        Ok((mut ws_stream, response)) => {
             info!("WebSocket connected to {}", &request.request.uri());
+            let response_metadata = HttpResponseMetadata::from_http_response(&response)?;
+            if let Some(ref message) = request.auth_message {
+                ws_stream.send(Message::Text(message.clone())).await?;
+                debug!("Auth message sent");
+            }
+            if let Some(ref ack) = request.await_ack {
+                await_ack(&mut ws_stream, ack).await?;
+                info!("WebSocket authentication acknowledged");
+            }
             for message in request.subscription_messages.iter().cloned() {
                 ws_stream.send(Message::Text(message)).await?;
             }
-            Ok(ws_stream)
+            Ok((ws_stream, response_metadata))
         }
         Err(e) => {
             error!("WebSocket connection error: {}", e);
-            Err(anyhow::Error::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                e,
-            )))
+            if let (Some(auth), WsError::Http(response)) = (auth, &e) {
+                if response.status() == http::StatusCode::UNAUTHORIZED {
+                    auth.invalidate().await;
+                }
+            }
+            Err(anyhow::Error::new(std::io::Error::other(e)))
         }
     }
 }
 
+/// Reads messages until one matches `ack`, failing when the connection closes or after `timeout_ms`
+async fn await_ack(ws_stream: &mut WebSocketStream<Transport>, ack: &AckConfig) -> Result<()> {
+    let timeout = Duration::from_millis(ack.timeout_ms);
+    let wait = async {
+        while let Some(message) = ws_stream.next().await {
+            match message.context("WebSocket read error while waiting for acknowledgement")? {
+                Message::Close(frame) => {
+                    return Err(anyhow!(
+                        "WebSocket closed before acknowledgement: {}",
+                        frame
+                            .map(|frame| frame.reason.into_owned())
+                            .unwrap_or_default()
+                    ))
+                }
+                message if matches(&ack.pattern, &message) => return Ok(()),
+                message => debug!("Ignoring message received before acknowledgement: {message}"),
+            }
+        }
+        Err(anyhow!("WebSocket closed before acknowledgement"))
+    };
+
+    tokio::time::timeout(timeout, wait).await.map_err(|_| {
+        anyhow!(
+            "no acknowledgement received within {}",
+            humantime::format_duration(timeout)
+        )
+    })?
+}
+
+/// Whether a text or binary `message` matches `pattern`
+fn matches(pattern: &MessagePattern, message: &Message) -> bool {
+    let data = match message {
+        Message::Text(text) => text.as_bytes(),
+        Message::Binary(data) => data,
+        _ => return false,
+    };
+
+    match pattern {
+        MessagePattern::Text(text) => data == text.as_bytes(),
+        MessagePattern::Json { pointer, value } => serde_json::from_slice::<Value>(data)
+            .is_ok_and(|json| json.pointer(pointer) == Some(value)),
+    }
+}
+
 async fn websocket_writer_and_stream<'a>(
     request: WSRequest,
-) -> Result<(WSPingOnlySink, LocalBoxStream<'a, String>)> {
-    let ws_stream = establish_connection(request)
+    record_key: Option<RecordKeyConfig>,
+    formatter: Arc<dyn Formatter + Sync + Send>,
+    auth: Option<&Authenticator>,
+) -> Result<(WSPingOnlySink, LocalBoxStream<'a, Frame>)> {
+    let endpoint = request.request.uri().to_string();
+    let keepalive_message = request.keepalive_message.clone();
+    let heartbeat_reply = request.heartbeat_reply.clone();
+    let (ws_stream, response_metadata) = establish_connection(request, auth)
         .await
         .context("Failed to establish WebSocket connection")?;
 
     let (write_half, read_half) = futures::stream::StreamExt::split(ws_stream);
-    let stream = futures::stream::StreamExt::filter_map(read_half, |message_result| {
-        async move {
-            match message_result {
-                Ok(message) => {
-                    match message {
-                        Message::Text(text) => Some(text),
-                        Message::Binary(data) => {
-                            if let Ok(text) = String::from_utf8(data) {
-                                Some(text)
-                            } else {
-                                error!("Received binary data that could not be converted to UTF-8 text");
-                                None
-                            }
-                        }
+    let metadata = response_metadata.clone();
+    let connection_id = format!("{:016x}", fastrand::u64(..));
+    debug!("WebSocket connection id {connection_id}");
+    let mut sequence = 0;
+    let mut record = move |data: Vec<u8>, opcode: FrameOpcode| {
+        sequence += 1;
+        let frame = FrameMetadata {
+            received_at: SystemTime::now(),
+            opcode,
+            sequence,
+            connection_id: connection_id.clone(),
+        };
+        let input =
+            HttpResponseRecord::new(response_metadata.clone(), data).with_frame(Some(frame));
+        let body = input.body.clone().unwrap_or_default();
 
-                        Message::Ping(_) | Message::Pong(_) => {
-                            // upon receiving ping messages tungstenite queues pong replies automatically
-                            debug!("Received ping/pong message, connection is alive");
-                            None
-                        }
-                        Message::Close(_) => {
-                            info!("Received WebSocket Close frame");
-                            None
-                        }
-                        _ => {
-                            // Ignore other message types
-                            None
-                        }
+        match formatter.format(&input) {
+            Ok(value) => Frame::Record(SourceRecord::new(
+                record_key.as_ref(),
+                &response_metadata,
+                &body,
+                value,
+            )),
+            Err(err) => {
+                error!("Error formatting WebSocket message: {err:?}");
+                Frame::FormatFailed(
+                    DeadLetter::new(
+                        DeadLetterKind::FormatFailed,
+                        endpoint.clone(),
+                        &response_metadata,
+                        &body,
+                    )
+                    .with_error(&err),
+                )
+            }
+        }
+    };
+    let stream = futures::stream::StreamExt::filter_map(read_half, move |message_result| {
+        futures::future::ready(match message_result {
+            Ok(message)
+                if heartbeat_reply
+                    .as_ref()
+                    .is_some_and(|pattern| matches(pattern, &message)) =>
+            {
+                debug!("Received heartbeat reply, connection is alive");
+                Some(Frame::Pong)
+            }
+            Ok(message) => {
+                match message {
+                    Message::Text(text) => Some(record(text.into_bytes(), FrameOpcode::Text)),
+                    // binary frames are produced as they are, e.g. protobuf payloads
+                    Message::Binary(data) => Some(record(data, FrameOpcode::Binary)),
+                    Message::Pong(_) => {
+                        debug!("Received pong message, connection is alive");
+                        Some(Frame::Pong)
+                    }
+                    Message::Ping(_) => {
+                        // upon receiving ping messages tungstenite queues pong replies automatically
+                        debug!("Received ping message, connection is alive");
+                        None
+                    }
+                    Message::Close(Some(frame)) => {
+                        info!(
+                            "Received WebSocket Close frame, code: {}, reason: \"{}\"",
+                            u16::from(frame.code),
+                            frame.reason
+                        );
+                        Some(Frame::Closed)
+                    }
+                    Message::Close(None) => {
+                        info!("Received WebSocket Close frame without status code");
+                        Some(Frame::Closed)
+                    }
+                    _ => {
+                        // Ignore other message types
+                        None
                     }
-                }
-                Err(e) => {
-                    error!("WebSocket read error: {}", e);
-                    // Depending on the error you may choose to stop and close or try to reconnect
-                    None
                 }
             }
-        }
+            // tungstenite stops reading before buffering a message or frame above the limits,
+            // and ends the stream after any read error
+            Err(WsError::Capacity(CapacityError::MessageTooLong { size, max_size })) => {
+                Some(Frame::Oversized {
+                    size,
+                    max_size,
+                    metadata: metadata.clone(),
+                })
+            }
+            Err(e) => {
+                error!("WebSocket read error: {}", e);
+                Some(Frame::Closed)
+            }
+        })
     });
 
     Ok((
-        WSPingOnlySink(write_half),
+        WSPingOnlySink(write_half, keepalive_message),
         futures::stream::StreamExt::boxed_local(stream),
     ))
 }
 
 impl WebSocketSource {
-    pub(crate) fn new(config: &HttpConfig) -> Result<Self> {
+    pub(crate) fn new(config: &HttpConfig, context: &SourceContext) -> Result<Self> {
         let ws_config = config.websocket_config.as_ref();
 
-        let mut request = Url::parse(&config.endpoint.resolve()?)?.into_client_request()?;
-        let headers = request.headers_mut();
-
-        for h in config.headers.iter() {
-            match h.resolve() {
-                Ok(h) => {
-                    if let Some((key, value)) = h.split_once(':') {
-                        headers.insert(
-                            http::HeaderName::from_bytes(key.as_bytes())?,
-                            value.parse()?,
-                        );
-                    } else {
-                        error!("Failed to split header");
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to resolve header: {}", e);
-                }
-            }
-        }
+        let vars = context.request.tick();
+        let mut request = context.request.url(&vars)?.as_str().into_client_request()?;
+        request
+            .headers_mut()
+            .extend(context.request.headers(&vars)?);
 
         let subscription_messages = if let Some(ws_config) = ws_config {
             let mut messages = ws_config
@@ -166,23 +380,90 @@ impl WebSocketSource {
             vec![]
         };
 
+        let auth_message = ws_config
+            .and_then(|c| c.auth_message.as_ref())
+            .map(|message| {
+                let template = Template::parse(&message.resolve()?)
+                    .context("parse websocket_config.auth_message")?;
+                vars.render(&template)
+            })
+            .transpose()?;
+
+        let connector = config
+            .tls
+            .as_ref()
+            .map(tls::client_config)
+            .transpose()?
+            .map(Connector::Rustls);
+        let proxy = config
+            .proxy
+            .as_ref()
+            .map(ProxySettings::websocket)
+            .transpose()?
+            .map(Arc::new);
+
+        let mut protocol = ProtocolConfig::default();
+        if let Some(max_message_size) = ws_config.and_then(|c| c.max_message_size) {
+            protocol.max_message_size = Some(max_message_size);
+        }
+        if let Some(max_frame_size) = ws_config.and_then(|c| c.max_frame_size) {
+            protocol.max_frame_size = Some(max_frame_size);
+        }
+
         Ok(Self {
             request: WSRequest {
                 request,
                 subscription_messages,
+                auth_message,
+                await_ack: ws_config.and_then(|c| c.await_ack.clone()),
+                keepalive_message: ws_config.and_then(|c| c.keepalive_message.clone()),
+                heartbeat_reply: ws_config.and_then(|c| c.heartbeat_reply.clone()),
+                connector,
+                proxy,
+                protocol,
             },
             ping_interval_ms: ws_config.and_then(|c| c.ping_interval_ms).unwrap_or(10_000),
+            pong_timeout: ws_config
+                .and_then(|c| c.pong_timeout_ms)
+                .map(Duration::from_millis),
+            idle_timeout: ws_config
+                .and_then(|c| c.idle_timeout_ms)
+                .map(Duration::from_millis),
+            record_key: config.record_key.clone(),
+            formatter: formatter(
+                config.output_type,
+                config.output_parts,
+                config.body_format,
+                context.endpoint_name.clone(),
+            ),
+            on_oversized_message: ws_config
+                .map(|c| c.on_oversized_message)
+                .unwrap_or_default(),
+            dead_letter: context.dead_letter.clone(),
+            auth: context.auth.clone(),
+            template: context.request.clone(),
+            vars,
         })
     }
 
-    async fn connect_and_run<'a>(self) -> Result<LocalBoxStream<'a, String>> {
+    async fn connect_and_run<'a>(self) -> Result<LocalBoxStream<'a, SourceRecord>> {
         enum StreamElement {
-            Read(String),
+            Read(Frame),
             PingInterval,
         }
 
-        let ws_stream_result = websocket_writer_and_stream(self.request.clone()).await?;
+        let ws_stream_result = websocket_writer_and_stream(
+            self.request.clone(),
+            self.record_key,
+            self.formatter.clone(),
+            self.auth.as_ref(),
+        )
+        .await?;
+        self.template.succeeded(&self.vars);
 
+        let pong_timeout = self.pong_timeout;
+        let idle_timeout = self.idle_timeout;
+        let endpoint = self.request.request.uri().to_string();
         let repeated_websocket = Box::pin(async_stream::stream! {
             let (mut ping_only, ws_stream) = ws_stream_result;
 
@@ -190,12 +471,55 @@ impl WebSocketSource {
                 .map(StreamElement::Read)
                 .merge(IntervalStream::new(tokio::time::interval(Duration::from_millis(self.ping_interval_ms))).map(|_| StreamElement::PingInterval));
 
-            while let Some(item) = ws_stream.next().await {
+            let mut last_data = Instant::now();
+            let mut unanswered_ping: Option<Instant> = None;
+            loop {
+                let idle_deadline = idle_timeout.map(|timeout| last_data + timeout);
+                let pong_deadline = unanswered_ping.zip(pong_timeout).map(|(sent, timeout)| sent + timeout);
+                let item = match idle_deadline.into_iter().chain(pong_deadline).min() {
+                    Some(deadline) => match tokio::time::timeout_at(deadline, ws_stream.next()).await {
+                        Ok(item) => item,
+                        Err(_) if idle_deadline == Some(deadline) => {
+                            warn!("No WebSocket message received for {}, reconnecting", humantime::format_duration(idle_timeout.unwrap_or_default()));
+                            break;
+                        }
+                        Err(_) => {
+                            warn!("No WebSocket pong received within {}, reconnecting", humantime::format_duration(pong_timeout.unwrap_or_default()));
+                            break;
+                        }
+                    },
+                    None => ws_stream.next().await,
+                };
+
                 match item {
-                    StreamElement::Read(s) => yield s,
-                    StreamElement::PingInterval => {
+                    Some(StreamElement::Read(Frame::Record(record))) => {
+                        last_data = Instant::now();
+                        yield record;
+                    }
+                    Some(StreamElement::Read(Frame::Pong)) => unanswered_ping = None,
+                    Some(StreamElement::Read(Frame::Oversized { size, max_size, metadata })) => {
+                        warn!("Received WebSocket message of {size} bytes, larger than the limit of {max_size} bytes, reconnecting");
+                        // the payload was not read, so the connection cannot continue past it
+                        // and dropping the message also reconnects
+                        if self.on_oversized_message == OversizedMessageAction::DeadLetter {
+                            if let Some(ref dead_letter) = self.dead_letter {
+                                let letter = DeadLetter::oversized_message(&endpoint, &metadata, size, max_size);
+                                dead_letter.send(letter).await;
+                            }
+                        }
+                        break;
+                    }
+                    Some(StreamElement::Read(Frame::FormatFailed(letter))) => {
+                        last_data = Instant::now();
+                        if let Some(ref dead_letter) = self.dead_letter {
+                            dead_letter.send(letter).await;
+                        }
+                    }
+                    Some(StreamElement::Read(Frame::Closed)) | None => break,
+                    Some(StreamElement::PingInterval) => {
                         let ping_res = ping_only.ping().await;
                         if ping_res.is_err() { break; }
+                        unanswered_ping.get_or_insert_with(Instant::now);
                     }
                 }
             }
@@ -206,10 +530,177 @@ impl WebSocketSource {
 }
 
 #[async_trait]
-impl<'a> Source<'a, String> for WebSocketSource {
-    async fn connect(self, _offset: Option<Offset>) -> Result<LocalBoxStream<'a, String>> {
-        self.connect_and_run()
+impl<'a> Source<'a, Result<SourceRecord>> for WebSocketSource {
+    async fn connect(
+        self,
+        _offset: Option<Offset>,
+    ) -> Result<LocalBoxStream<'a, Result<SourceRecord>>> {
+        let stream = self
+            .connect_and_run()
+            .await
+            .context("Failed to run WebSocket connection")?;
+
+        Ok(futures::stream::StreamExt::boxed_local(
+            futures::stream::StreamExt::map(stream, Ok),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::{
+        accept_async, connect_async,
+        tungstenite::protocol::{
+            frame::{
+                coding::{CloseCode, Data, OpCode},
+                Frame as WsFrame,
+            },
+            CloseFrame,
+        },
+    };
+
+    use super::*;
+
+    fn ack() -> AckConfig {
+        serde_json::from_str(r#"{"pointer": "/status", "value": "ok", "timeout_ms": 500}"#).unwrap()
+    }
+
+    /// Waits for the acknowledgement after the server sends `messages`
+    async fn await_ack_after(messages: Vec<Message>) -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        async_std::task::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut server = accept_async(stream).await.unwrap();
+            for message in messages {
+                server.send(message).await.unwrap();
+            }
+            // keep the connection open until the client is done
+            let _ = server.next().await;
+        });
+
+        let (mut client, _) = connect_async(format!("ws://{address}")).await.unwrap();
+        await_ack(&mut client, &ack()).await
+    }
+
+    /// Values of the records produced while the server sends `messages` and closes the connection
+    async fn records_after(messages: Vec<Message>, websocket_config: &str) -> Vec<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        async_std::task::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut server = accept_async(stream).await.unwrap();
+            for message in messages {
+                server.send(message).await.unwrap();
+            }
+        });
+
+        let config: HttpConfig = serde_json::from_str(&format!(
+            r#"{{"endpoint": "ws://{address}", "websocket_config": {websocket_config}}}"#
+        ))
+        .unwrap();
+        let context = SourceContext::new(&config, None, None, None).await.unwrap();
+        let stream = WebSocketSource::new(&config, &context)
+            .unwrap()
+            .connect_and_run()
+            .await
+            .unwrap();
+
+        stream
+            .map(|record| String::from_utf8(record.value).unwrap())
+            .collect()
             .await
-            .context("Failed to run WebSocket connection")
+    }
+
+    #[async_std::test]
+    async fn test_oversized_message() {
+        let messages = |oversized: Vec<Message>| {
+            let mut messages = vec![Message::Text("small 1".into())];
+            messages.extend(oversized);
+            messages.push(Message::Text("small 2".into()));
+            messages
+        };
+        // 2048 bytes sent in frames of 512 bytes
+        let fragmented = || {
+            (0..4)
+                .map(|i| {
+                    let opcode = match i {
+                        0 => OpCode::Data(Data::Text),
+                        _ => OpCode::Data(Data::Continue),
+                    };
+                    Message::Frame(WsFrame::message(vec![b'x'; 512], opcode, i == 3))
+                })
+                .collect()
+        };
+
+        let records = records_after(
+            messages(vec![]),
+            r#"{"max_message_size": 1024, "max_frame_size": 1024}"#,
+        )
+        .await;
+        assert_eq!(records, vec!["small 1", "small 2"]);
+
+        // the payload of an oversized message is not read, so every action reconnects
+        for action in ["drop", "reconnect"] {
+            let config =
+                format!(r#"{{"max_message_size": 1024, "on_oversized_message": "{action}"}}"#);
+            let records =
+                records_after(messages(vec![Message::Text("x".repeat(2048))]), &config).await;
+            assert_eq!(records, vec!["small 1"]);
+
+            let records = records_after(messages(fragmented()), &config).await;
+            assert_eq!(records, vec!["small 1"]);
+        }
+
+        let records = records_after(
+            messages(vec![Message::Binary(vec![0; 2048])]),
+            r#"{"max_frame_size": 1024}"#,
+        )
+        .await;
+        assert_eq!(records, vec!["small 1"]);
+    }
+
+    #[test]
+    fn test_matches() {
+        let json = ack().pattern;
+        assert!(matches(&json, &Message::Text(r#"{"status": "ok"}"#.into())));
+        assert!(matches(
+            &json,
+            &Message::Binary(br#"{"status":"ok"}"#.to_vec())
+        ));
+        assert!(!matches(
+            &json,
+            &Message::Text(r#"{"status": "error"}"#.into())
+        ));
+        assert!(!matches(&json, &Message::Text("ok".into())));
+        assert!(!matches(&json, &Message::Ping(Vec::new())));
+
+        let text: MessagePattern = serde_json::from_str(r#""pong""#).unwrap();
+        assert!(matches(&text, &Message::Text("pong".into())));
+        assert!(!matches(&text, &Message::Text("pong 1".into())));
+    }
+
+    #[async_std::test]
+    async fn test_await_ack() {
+        await_ack_after(vec![
+            Message::Text("welcome".into()),
+            Message::Text(r#"{"event": "auth", "status": "ok"}"#.into()),
+        ])
+        .await
+        .unwrap();
+
+        let err = await_ack_after(vec![Message::Text(r#"{"status": "error"}"#.into())])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no acknowledgement received"));
+
+        let err = await_ack_after(vec![Message::Close(Some(CloseFrame {
+            code: CloseCode::Policy,
+            reason: "invalid key".into(),
+        }))])
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid key"));
     }
 }
@@ -12,12 +12,14 @@ use tide_websockets::{Message, WebSocket};
 struct State {
     get_count: Arc<AtomicU32>,
     post_count: Arc<AtomicU32>,
+    flaky_count: Arc<AtomicU32>,
 }
 impl State {
     fn new() -> Self {
         Self {
             get_count: Arc::new(AtomicU32::new(0)),
             post_count: Arc::new(AtomicU32::new(0)),
+            flaky_count: Arc::new(AtomicU32::new(0)),
         }
     }
 }
@@ -28,6 +30,11 @@ async fn main() -> tide::Result<()> {
     app.at("/get").get(get_request);
     app.at("/time").get(get_time_request);
     app.at("/post").post(post_request);
+    app.at("/items").get(get_items_request);
+    app.at("/items/:id").get(get_item_request);
+    app.at("/flaky").get(get_flaky_request);
+    app.at("/gzip").get(get_gzip_request);
+    app.at("/binary").get(get_binary_request);
     app.at("/stream_count_updates")
         .get(sse::endpoint(stream_count_updates));
     app.at("/websocket")
@@ -39,6 +46,15 @@ async fn main() -> tide::Result<()> {
             }
             Ok(())
         }));
+    app.at("/websocket-idle")
+        .get(WebSocket::new(|_request, stream| async move {
+            stream
+                .send_string("Hello, idle Fluvio!".to_string())
+                .await?;
+            // keep the connection open without sending anything else
+            async_std::task::sleep(std::time::Duration::from_secs(60)).await;
+            Ok(())
+        }));
     app.at("/websocket-auth")
         .get(WebSocket::new(|request, stream| async move {
             let header_values = request.header("x-secret-token");
@@ -52,6 +68,41 @@ async fn main() -> tide::Result<()> {
                     .send_string(format!("Hello, Fluvio! - {}", i))
                     .await?;
             }
+            Ok(())
+        }));
+    app.at("/websocket-login")
+        .get(WebSocket::new(|_request, mut stream| async move {
+            // subscriptions are only answered after an auth message with the right key
+            match stream.next().await {
+                Some(Ok(Message::Text(auth))) if auth.contains(r#""key":"abc123""#) => {
+                    stream
+                        .send_string(r#"{"event":"auth","status":"ok"}"#.to_string())
+                        .await?;
+                }
+                _ => {
+                    stream
+                        .send_string(r#"{"event":"auth","status":"error"}"#.to_string())
+                        .await?;
+                    return Ok(());
+                }
+            }
+
+            while let Some(Ok(Message::Text(input))) = stream.next().await {
+                stream.send_string(input.to_uppercase()).await?;
+            }
+
+            Ok(())
+        }));
+    app.at("/websocket-heartbeat")
+        .get(WebSocket::new(|_request, mut stream| async move {
+            while let Some(Ok(Message::Text(input))) = stream.next().await {
+                if input == r#"{"op":"ping"}"# {
+                    stream.send_string(r#"{"op":"pong"}"#.to_string()).await?;
+                } else {
+                    stream.send_string(input.to_uppercase()).await?;
+                }
+            }
+
             Ok(())
         }));
     app.at("/websocket-echo")
@@ -83,6 +134,76 @@ async fn get_time_request(_req: Request<State>) -> tide::Result {
     Ok(format!("{time}").into())
 }
 
+const ITEMS_PAGE_SIZE: u32 = 2;
+const ITEMS_TOTAL: u32 = 5;
+
+#[derive(Debug, Deserialize)]
+struct ItemsQuery {
+    page: Option<u32>,
+}
+
+async fn get_items_request(req: Request<State>) -> tide::Result {
+    let ItemsQuery { page } = req.query()?;
+    let page = page.unwrap_or(1).max(1);
+
+    let first = (page - 1) * ITEMS_PAGE_SIZE + 1;
+    let last = (first + ITEMS_PAGE_SIZE).min(ITEMS_TOTAL + 1);
+    let data: Vec<_> = (first..last).map(|id| json!({ "id": id })).collect();
+
+    Ok(json!({ "page": page, "data": data }).into())
+}
+
+async fn get_item_request(req: Request<State>) -> tide::Result {
+    let id: u32 = req.param("id")?.parse()?;
+
+    Ok(json!({ "id": id, "name": format!("item {id}") }).into())
+}
+
+/// "Hello, compressed Fluvio!" compressed with gzip
+const GZIP_BODY: [u8; 45] = [
+    0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xf3, 0x48, 0xcd, 0xc9, 0xc9, 0xd7,
+    0x51, 0x48, 0xce, 0xcf, 0x2d, 0x28, 0x4a, 0x2d, 0x2e, 0x4e, 0x4d, 0x51, 0x70, 0xcb, 0x29, 0x2d,
+    0xcb, 0xcc, 0x57, 0x04, 0x00, 0x9a, 0x1e, 0x49, 0x8d, 0x19, 0x00, 0x00, 0x00,
+];
+
+/// Responds with a gzip body when the request accepts it
+async fn get_gzip_request(req: Request<State>) -> tide::Result {
+    let accepts_gzip = req
+        .header("Accept-Encoding")
+        .is_some_and(|values| values.as_str().contains("gzip"));
+    if !accepts_gzip {
+        return Ok("Hello, uncompressed Fluvio!".into());
+    }
+
+    let mut response = tide::Response::new(200);
+    response.insert_header("Content-Encoding", "gzip");
+    response.set_body(GZIP_BODY.to_vec());
+    Ok(response)
+}
+
+/// Protobuf encoded message with field 1 set to 150, followed by a byte that is not valid UTF-8
+const BINARY_BODY: [u8; 4] = [0x08, 0x96, 0x01, 0xff];
+
+async fn get_binary_request(_req: Request<State>) -> tide::Result {
+    let mut response = tide::Response::new(200);
+    response.set_body(BINARY_BODY.to_vec());
+    Ok(response)
+}
+
+/// Responds with 503 and `Retry-After` to every other request
+async fn get_flaky_request(req: Request<State>) -> tide::Result {
+    let state = req.state();
+    let value = state.flaky_count.fetch_add(1, Ordering::Relaxed) + 1;
+    if value % 2 == 1 {
+        let mut response = tide::Response::new(503);
+        response.insert_header("Retry-After", "1");
+        response.set_body("Service Unavailable");
+        return Ok(response);
+    }
+
+    Ok(format!("Hello, Fluvio! - {}", value / 2).into())
+}
+
 #[derive(Debug, Deserialize)]
 struct HelloPostBody {
     name: String,
@@ -0,0 +1,36 @@
+#!/usr/bin/env bats
+
+setup() {
+    cargo build -p mock-http-server
+    ./target/debug/mock-http-server & disown
+    MOCK_PID=$!
+    FILE=$(mktemp)
+    cp ./tests/get-multi-endpoint-test-config.yaml $FILE
+    UUID=$(uuidgen | awk '{print tolower($0)}')
+    TOPIC=${UUID}-topic
+    fluvio topic create $TOPIC
+
+    sed -i.BAK "s/TOPIC/${TOPIC}/g" $FILE
+    cat $FILE
+
+    cargo build -p http-source
+    ./target/debug/http-source --config $FILE & disown
+    CONNECTOR_PID=$!
+}
+
+teardown() {
+    fluvio topic delete $TOPIC
+    kill $MOCK_PID
+    kill $CONNECTOR_PID
+}
+
+@test "http-connector-multi-endpoint-test" {
+    echo "Starting consumer on topic $TOPIC"
+    sleep 13
+
+    run fluvio consume -B -d -k --end 10 $TOPIC
+    echo "$output"
+    [ "$status" -eq 0 ]
+    [[ "$output" == *"[get] Hello, Fluvio!"* ]]
+    [[ "$output" == *"[post] Hello, Endpoints!"* ]]
+}
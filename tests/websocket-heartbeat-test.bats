@@ -0,0 +1,35 @@
+#!/usr/bin/env bats
+
+setup() {
+    cargo build -p mock-http-server
+    ./target/debug/mock-http-server & disown
+    MOCK_PID=$!
+    FILE=$(mktemp)
+    cp ./tests/websocket-heartbeat-test-config.yaml $FILE
+    UUID=$(uuidgen | awk '{print tolower($0)}')
+    TOPIC=${UUID}-topic
+    fluvio topic create $TOPIC
+
+    sed -i.BAK "s/TOPIC/${TOPIC}/g" $FILE
+    cat $FILE
+
+    cargo build -p http-source
+    ./target/debug/http-source --config $FILE & disown
+    CONNECTOR_PID=$!
+}
+
+teardown() {
+    fluvio topic delete $TOPIC
+    kill $MOCK_PID
+    kill $CONNECTOR_PID
+}
+
+@test "websocket-heartbeat-test" {
+    echo "Starting consumer on topic $TOPIC"
+    sleep 13
+
+    # heartbeat replies are not produced
+    run fluvio consume -B -d $TOPIC
+    echo "$output"
+    [ "$output" = "$(printf 'HELLO 1\nHELLO 2\nHELLO 3')" ]
+}
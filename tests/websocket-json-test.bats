@@ -0,0 +1,42 @@
+#!/usr/bin/env bats
+
+load './bats-helpers/bats-support/load'
+load './bats-helpers/bats-assert/load'
+
+setup() {
+    cargo build -p mock-http-server
+    ./target/debug/mock-http-server & disown
+    MOCK_PID=$!
+    FILE=$(mktemp)
+    cp ./tests/websocket-json-test-config.yaml $FILE
+    UUID=$(uuidgen | awk '{print tolower($0)}')
+    TOPIC=${UUID}-topic
+    fluvio topic create $TOPIC
+
+    sed -i.BAK "s/TOPIC/${TOPIC}/g" $FILE
+    cat $FILE
+
+    cargo build -p http-source
+    ./target/debug/http-source --config $FILE & disown
+    CONNECTOR_PID=$!
+}
+
+teardown() {
+    fluvio topic delete $TOPIC
+    kill $MOCK_PID
+    kill $CONNECTOR_PID
+}
+
+@test "websocket-json-test" {
+    echo "Starting consumer on topic $TOPIC"
+    sleep 5
+
+    run fluvio consume --start 0 --end 0 -d $TOPIC
+    assert_output --partial '"status":{"version":"HTTP/1.1","code":101,"string":"Switching Protocols"}'
+    assert_output --partial '"body":"Hello, Fluvio! - 1","frame":{"received_at":'
+    assert_output --partial '"opcode":"text","sequence":1,"connection_id":'
+
+    run fluvio consume --start 1 --end 1 -d $TOPIC
+    assert_output --partial '"body":"Hello, Fluvio! - 2","frame":{"received_at":'
+    assert_output --partial '"opcode":"text","sequence":2,"connection_id":'
+}